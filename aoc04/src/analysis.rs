@@ -0,0 +1,100 @@
+use crate::SleepRecord;
+use std::collections::HashMap;
+
+/// z-score for a 95% confidence interval.
+const Z: f64 = 1.96;
+
+/// How likely a guard is to be asleep at a given minute, normalized by the shifts they worked.
+#[derive(Debug, Copy, Clone)]
+pub struct Estimate {
+    pub guard: usize,
+    pub minute: usize,
+    /// Observed fraction of shifts on which the guard was asleep at this minute.
+    pub probability: f64,
+    /// Bounds of the 95% Wilson score interval around `probability`. The fewer shifts observed,
+    /// the wider the interval.
+    pub lower: f64,
+    pub upper: f64,
+    pub shifts: usize,
+}
+
+impl Estimate {
+    fn new(guard: usize, minute: usize, record: &SleepRecord) -> Option<Estimate> {
+        let shifts = record.nightly_minutes_asleep.len();
+        if shifts == 0 {
+            return None;
+        }
+        let n = shifts as f64;
+        let probability = record.sleep_histogram[minute] as f64 / n;
+        let denominator = 1.0 + Z * Z / n;
+        let center = (probability + Z * Z / (2.0 * n)) / denominator;
+        let margin = Z * (probability * (1.0 - probability) / n + Z * Z / (4.0 * n * n)).sqrt()
+            / denominator;
+        Some(Estimate {
+            guard,
+            minute,
+            probability,
+            lower: (center - margin).max(0.0),
+            upper: (center + margin).min(1.0),
+            shifts,
+        })
+    }
+}
+
+impl std::fmt::Display for Estimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "guard #{} at minute {}: asleep with p = {:.3} (95% CI {:.3}..{:.3}, {} shifts)",
+            self.guard, self.minute, self.probability, self.lower, self.upper, self.shifts
+        )
+    }
+}
+
+/// Per-guard, per-minute probabilities of being asleep.
+pub struct SleepModel {
+    estimates: Vec<Estimate>,
+}
+
+impl SleepModel {
+    pub fn new(guard_to_sleep_record: &HashMap<usize, SleepRecord>) -> SleepModel {
+        let mut estimates: Vec<Estimate> = guard_to_sleep_record
+            .iter()
+            .flat_map(|(&guard, record)| {
+                (0..60).filter_map(move |minute| Estimate::new(guard, minute, record))
+            })
+            .collect();
+        estimates.sort_by_key(|estimate| (estimate.guard, estimate.minute));
+        SleepModel { estimates }
+    }
+
+    /// Every guard's estimate at `minute`, safest to sneak past first. Guards are ranked by the
+    /// lower bound of their interval so that a guard seen asleep on one shift out of one does not
+    /// outrank a guard seen asleep on 18 out of 20.
+    pub fn safest_guards_at(&self, minute: usize) -> Vec<Estimate> {
+        let mut estimates: Vec<Estimate> = self
+            .estimates
+            .iter()
+            .filter(|estimate| estimate.minute == minute)
+            .cloned()
+            .collect();
+        estimates.sort_by(by_lower_bound_descending);
+        estimates
+    }
+
+    /// The guard and minute with the most confident chance of finding the guard asleep.
+    pub fn best_minute(&self) -> Option<Estimate> {
+        self.estimates
+            .iter()
+            .cloned()
+            .min_by(by_lower_bound_descending)
+    }
+}
+
+fn by_lower_bound_descending(a: &Estimate, b: &Estimate) -> std::cmp::Ordering {
+    b.lower
+        .partial_cmp(&a.lower)
+        .unwrap_or(std::cmp::Ordering::Equal)
+        .then(a.guard.cmp(&b.guard))
+        .then(a.minute.cmp(&b.minute))
+}
@@ -0,0 +1,252 @@
+use crate::Result;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// (year, month, day, hour, minute)
+type Timestamp = (u32, u32, u32, u32, u32);
+
+static RUN_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Most runs merged at once, so merging never has more files open than this.
+const MAX_MERGE_FAN_IN: usize = 64;
+
+/// Expands the given paths into the list of log files to read. Directories contribute every file
+/// directly inside them, in name order.
+pub fn log_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut dir_files = std::fs::read_dir(path)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<std::io::Result<Vec<PathBuf>>>()?;
+            dir_files.retain(|file| file.is_file());
+            dir_files.sort();
+            files.extend(dir_files);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+/// Reads every line from `files` and yields them ordered by timestamp, with identical lines
+/// removed. At most `max_lines_in_memory` lines are held at once; anything beyond that is sorted
+/// in runs that are spilled to temporary files and merged back together, at most
+/// `MAX_MERGE_FAN_IN` at a time.
+pub fn sorted_unique_lines(files: &[PathBuf], max_lines_in_memory: usize) -> Result<SortedLines> {
+    let max_lines_in_memory = std::cmp::max(max_lines_in_memory, 1);
+    let mut runs = Vec::new();
+    let mut buffer: Vec<(Timestamp, String)> = Vec::new();
+    for file in files {
+        for line in BufReader::new(File::open(file)?).lines() {
+            let line = line?;
+            let line = line.trim_end();
+            if line.is_empty() {
+                continue;
+            }
+            buffer.push((parse_timestamp(line)?, line.to_string()));
+            if buffer.len() >= max_lines_in_memory {
+                runs.push(Run::spill(&mut buffer)?);
+            }
+        }
+    }
+    sort_and_dedup(&mut buffer);
+
+    while runs.len() >= MAX_MERGE_FAN_IN {
+        let mut merged_runs = Vec::with_capacity(runs.len() / MAX_MERGE_FAN_IN + 1);
+        let mut runs_left = runs.into_iter();
+        loop {
+            let batch: Vec<Run> = runs_left.by_ref().take(MAX_MERGE_FAN_IN).collect();
+            if batch.is_empty() {
+                break;
+            }
+            merged_runs.push(Run::merge(batch)?);
+        }
+        runs = merged_runs;
+    }
+
+    let mut sources: Vec<Source> = runs
+        .into_iter()
+        .map(Run::open)
+        .collect::<Result<Vec<Source>>>()?;
+    sources.push(Source::Memory(buffer.into_iter()));
+    SortedLines::new(sources)
+}
+
+fn parse_timestamp(line: &str) -> Result<Timestamp> {
+    lazy_static! {
+        static ref TIMESTAMP_REGEX: Regex =
+            Regex::new(r"^\[(\d{4})-(\d{2})-(\d{2}) (\d{2}):(\d{2})\]").unwrap();
+    }
+    let captures = TIMESTAMP_REGEX
+        .captures(line)
+        .ok_or_else(|| format!("Can't parse timestamp: {}", line))?;
+    Ok((
+        captures[1].parse()?,
+        captures[2].parse()?,
+        captures[3].parse()?,
+        captures[4].parse()?,
+        captures[5].parse()?,
+    ))
+}
+
+fn sort_and_dedup(entries: &mut Vec<(Timestamp, String)>) {
+    entries.sort_unstable();
+    entries.dedup();
+}
+
+/// A sorted, deduplicated run of lines spilled to a temporary file. The file is removed on drop.
+struct Run {
+    path: PathBuf,
+}
+
+impl Run {
+    fn spill(buffer: &mut Vec<(Timestamp, String)>) -> Result<Run> {
+        sort_and_dedup(buffer);
+        Run::write(buffer.drain(..).map(|(_timestamp, line)| Ok(line)))
+    }
+
+    /// Merges several runs into one, removing the originals.
+    fn merge(runs: Vec<Run>) -> Result<Run> {
+        let sources = runs
+            .into_iter()
+            .map(Run::open)
+            .collect::<Result<Vec<Source>>>()?;
+        Run::write(SortedLines::new(sources)?)
+    }
+
+    /// Writes already sorted lines to a new temporary file.
+    fn write(lines: impl Iterator<Item = Result<String>>) -> Result<Run> {
+        let path = std::env::temp_dir().join(format!(
+            "aoc04-run-{}-{}",
+            std::process::id(),
+            RUN_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        let run = Run { path };
+        let mut writer = BufWriter::new(file);
+        for line in lines {
+            writeln!(writer, "{}", line?)?;
+        }
+        writer.flush()?;
+        Ok(run)
+    }
+
+    fn open(self) -> Result<Source> {
+        let lines = BufReader::new(File::open(&self.path)?).lines();
+        Ok(Source::Disk { lines, _run: self })
+    }
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+enum Source {
+    Memory(std::vec::IntoIter<(Timestamp, String)>),
+    Disk {
+        lines: Lines<BufReader<File>>,
+        _run: Run,
+    },
+}
+
+impl Source {
+    fn next_entry(&mut self) -> Result<Option<(Timestamp, String)>> {
+        match self {
+            Source::Memory(entries) => Ok(entries.next()),
+            Source::Disk { lines, .. } => match lines.next() {
+                Some(line) => {
+                    let line = line?;
+                    Ok(Some((parse_timestamp(&line)?, line)))
+                }
+                None => Ok(None),
+            },
+        }
+    }
+}
+
+/// k-way merge over the sorted runs, skipping lines identical to the one before.
+pub struct SortedLines {
+    sources: Vec<Source>,
+    heap: BinaryHeap<Reverse<((Timestamp, String), usize)>>,
+    previous: Option<String>,
+}
+
+impl SortedLines {
+    fn new(mut sources: Vec<Source>) -> Result<SortedLines> {
+        let mut heap = BinaryHeap::with_capacity(sources.len());
+        for (idx, source) in sources.iter_mut().enumerate() {
+            if let Some(entry) = source.next_entry()? {
+                heap.push(Reverse((entry, idx)));
+            }
+        }
+        Ok(SortedLines {
+            sources,
+            heap,
+            previous: None,
+        })
+    }
+
+    fn next_line(&mut self) -> Result<Option<String>> {
+        while let Some(Reverse(((_timestamp, line), idx))) = self.heap.pop() {
+            if let Some(entry) = self.sources[idx].next_entry()? {
+                self.heap.push(Reverse((entry, idx)));
+            }
+            if self.previous.as_ref() != Some(&line) {
+                self.previous = Some(line.clone());
+                return Ok(Some(line));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Iterator for SortedLines {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_line().transpose()
+    }
+}
+
+#[test]
+fn test_sorted_unique_lines_across_runs() {
+    let line =
+        |day: u32, minute: u32| format!("[1518-11-{:02} 00:{:02}] falls asleep", day, minute);
+    // one run per line, enough to need more than one round of merging; days 2 and 3 are in both
+    // files, and both files are in reverse order
+    let first: Vec<String> = (1..=3)
+        .rev()
+        .flat_map(|day| (0..60).rev().map(move |minute| line(day, minute)))
+        .collect();
+    let second: Vec<String> = (2..=4)
+        .rev()
+        .flat_map(|day| (0..60).rev().map(move |minute| line(day, minute)))
+        .collect();
+    let dir = std::env::temp_dir().join(format!("aoc04-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let paths = [dir.join("first"), dir.join("second")];
+    std::fs::write(&paths[0], first.join("\n")).unwrap();
+    std::fs::write(&paths[1], second.join("\n")).unwrap();
+
+    let lines = sorted_unique_lines(&paths, 1)
+        .unwrap()
+        .collect::<Result<Vec<String>>>();
+    std::fs::remove_dir_all(&dir).unwrap();
+    let expected: Vec<String> = (1..=4)
+        .flat_map(|day| (0..60).map(move |minute| line(day, minute)))
+        .collect();
+    assert!(first.len() + second.len() > MAX_MERGE_FAN_IN);
+    assert_eq!(lines.unwrap(), expected);
+}
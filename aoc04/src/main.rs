@@ -1,12 +1,68 @@
-use itertools::Itertools;
+use analysis::SleepModel;
 use lazy_static::lazy_static;
 use regex::Regex;
 use serde_scan::scan;
+use std::cmp::max;
 use std::collections::HashMap;
+use std::path::PathBuf;
+use strategy::{MostFrequentMinute, MostMinutesAsleep, Strategy};
+
+mod analysis;
+mod ingest;
+mod strategy;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 type SleepHistogram = [usize; 60];
 
+/// Everything we know about a single guard's sleeping habits.
+#[derive(Debug, Clone)]
+struct SleepRecord {
+    sleep_histogram: SleepHistogram,
+    /// Minutes asleep on each shift the guard worked, in order.
+    nightly_minutes_asleep: Vec<usize>,
+    longest_nap: usize,
+}
+
+impl Default for SleepRecord {
+    fn default() -> Self {
+        SleepRecord {
+            sleep_histogram: [0; 60],
+            nightly_minutes_asleep: Vec::new(),
+            longest_nap: 0,
+        }
+    }
+}
+
+impl SleepRecord {
+    fn record_nap(&mut self, sleep_time: usize, wake_time: usize) {
+        for times_asleep in &mut self.sleep_histogram[sleep_time..wake_time] {
+            *times_asleep += 1;
+        }
+        let nap_length = wake_time - sleep_time;
+        // naps logged before any shift began still count towards a night
+        if self.nightly_minutes_asleep.is_empty() {
+            self.nightly_minutes_asleep.push(0);
+        }
+        *self.nightly_minutes_asleep.last_mut().unwrap() += nap_length;
+        self.longest_nap = max(self.longest_nap, nap_length);
+    }
+
+    fn total_minutes_asleep(&self) -> usize {
+        self.sleep_histogram.iter().sum()
+    }
+
+    fn minute_asleep_most(&self) -> Option<usize> {
+        if self.total_minutes_asleep() == 0 {
+            return None;
+        }
+        self.sleep_histogram
+            .iter()
+            .enumerate()
+            .max_by_key(|(_minute, times_asleep)| *times_asleep)
+            .map(|(minute, _times_asleep)| minute)
+    }
+}
+
 #[derive(Debug)]
 enum Event {
     BeginsShift { guard_id: usize },
@@ -14,77 +70,166 @@ enum Event {
     WakesUp { wake_time: usize },
 }
 
-fn main() -> Result<()> {
-    let input = std::fs::read_to_string("input")?;
-    let events: Vec<Event> = input
-        .lines()
-        .sorted()
-        .map(parse_event)
-        .collect::<Result<Vec<Event>>>()?;
-    let guard_to_sleep_histograms = make_guard_to_sleep_histogram_map(events);
-    println!("Part 1: {}", part1(&guard_to_sleep_histograms)?);
-    println!("Part 2: {}", part2(&guard_to_sleep_histograms)?);
-    Ok(())
+/// Lines held in memory at once while sorting the logs, before spilling to disk.
+const DEFAULT_MAX_LINES_IN_MEMORY: usize = 1_000_000;
+
+#[derive(Debug)]
+struct Options {
+    strategy: Option<String>,
+    sneak_at: Option<usize>,
+    best_minute: bool,
+    max_lines_in_memory: usize,
+    paths: Vec<PathBuf>,
 }
 
-fn part1(guard_to_sleep_historgram: &HashMap<usize, SleepHistogram>) -> Result<usize> {
-    let sleepiest_guard = guard_to_sleep_historgram
-        .iter()
-        .max_by_key(|(_, sleep_histogram)| sleep_histogram.iter().sum::<usize>())
-        .map(|(guard, _total_sleep_time)| guard)
-        .ok_or("unable to find guard with max sleep time")?;
-
-    let minute_asleep_most = guard_to_sleep_historgram
-        .get(sleepiest_guard)
-        .ok_or("guard missing from map")?
-        .iter()
-        .enumerate()
-        .max_by_key(|(_idx, val)| *val)
-        .map(|(idx, _val)| idx)
-        .ok_or("unable to find max val in histogram")?;
-
-    Ok(sleepiest_guard * minute_asleep_most)
+fn main() -> Result<()> {
+    let options = parse_args(std::env::args().skip(1))?;
+    let files = ingest::log_files(&options.paths)?;
+    let mut guard_log = GuardLog::default();
+    for line in ingest::sorted_unique_lines(&files, options.max_lines_in_memory)? {
+        guard_log.record(parse_event(&line?)?)?;
+    }
+    let guard_to_sleep_record = guard_log.into_sleep_records();
+
+    if let Some(name) = &options.strategy {
+        let strategies = if name == "all" {
+            strategy::all_strategies()
+        } else {
+            vec![strategy::strategy_by_name(name)
+                .ok_or_else(|| format!("unknown strategy: {}", name))?]
+        };
+        for strategy in strategies {
+            let rankings = strategy::rank(strategy.as_ref(), &guard_to_sleep_record);
+            println!(
+                "{}",
+                strategy::format_rankings(strategy.as_ref(), &rankings)
+            );
+        }
+    }
+    if options.sneak_at.is_some() || options.best_minute {
+        let model = SleepModel::new(&guard_to_sleep_record);
+        if let Some(minute) = options.sneak_at {
+            println!("Safest guards to sneak past at minute {}:", minute);
+            for estimate in model.safest_guards_at(minute) {
+                println!("  {}", estimate);
+            }
+        }
+        if options.best_minute {
+            let best = model.best_minute().ok_or("no shifts in log")?;
+            println!("Best minute across all guards: {}", best);
+        }
+    }
+    if options.strategy.is_none() && options.sneak_at.is_none() && !options.best_minute {
+        println!("Part 1: {}", part1(&guard_to_sleep_record)?);
+        println!("Part 2: {}", part2(&guard_to_sleep_record)?);
+    }
+    Ok(())
 }
 
-fn part2(guard_to_sleep_histogram: &HashMap<usize, SleepHistogram>) -> Result<usize> {
-    // find the guard that is most frequenty asleep on the same minute * that minute
-    let mut guard_most_freq_asleep_on_same_minute: usize = 0;
-    let mut res_minute: usize = 0;
-    let mut max_times_asleep: usize = 0;
-    for (guard, sleep_histogram) in guard_to_sleep_histogram {
-        let (minute, times_asleep) = sleep_histogram
-            .iter()
-            .enumerate()
-            .max_by_key(|(_minute, times)| *times)
-            .ok_or("err")?;
-        if *times_asleep > max_times_asleep {
-            max_times_asleep = *times_asleep;
-            res_minute = minute;
-            guard_most_freq_asleep_on_same_minute = *guard;
+/// usage: aoc04 [--strategy <name>|all] [--sneak-at <minute>] [--best-minute] [--max-lines <n>] [<file or directory>...]
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options> {
+    let mut options = Options {
+        strategy: None,
+        sneak_at: None,
+        best_minute: false,
+        max_lines_in_memory: DEFAULT_MAX_LINES_IN_MEMORY,
+        paths: Vec::new(),
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--strategy" => {
+                options.strategy = Some(args.next().ok_or("--strategy requires a name")?);
+            }
+            "--sneak-at" => {
+                let minute: usize = args.next().ok_or("--sneak-at requires a minute")?.parse()?;
+                if minute >= 60 {
+                    return Err(From::from(format!("minute out of range: {}", minute)));
+                }
+                options.sneak_at = Some(minute);
+            }
+            "--best-minute" => options.best_minute = true,
+            "--max-lines" => {
+                options.max_lines_in_memory =
+                    args.next().ok_or("--max-lines requires a value")?.parse()?;
+            }
+            _ => options.paths.push(PathBuf::from(arg)),
         }
     }
-    Ok(res_minute * guard_most_freq_asleep_on_same_minute)
+    if options.paths.is_empty() {
+        options.paths.push(PathBuf::from("input"));
+    }
+    Ok(options)
 }
 
-fn make_guard_to_sleep_histogram_map(events: Vec<Event>) -> HashMap<usize, SleepHistogram> {
-    let mut guard_to_sleep_histogram = HashMap::new();
-    let mut current_guard = 0;
-    let mut sleep_start_time = 0;
-    for event in events {
+fn part1(guard_to_sleep_record: &HashMap<usize, SleepRecord>) -> Result<usize> {
+    best_guard_times_minute(&MostMinutesAsleep, guard_to_sleep_record)
+}
+
+fn part2(guard_to_sleep_record: &HashMap<usize, SleepRecord>) -> Result<usize> {
+    best_guard_times_minute(&MostFrequentMinute, guard_to_sleep_record)
+}
+
+fn best_guard_times_minute(
+    strategy: &dyn Strategy,
+    guard_to_sleep_record: &HashMap<usize, SleepRecord>,
+) -> Result<usize> {
+    let best = strategy::rank(strategy, guard_to_sleep_record)
+        .into_iter()
+        .next()
+        .ok_or("no guards in log")?;
+    let minute = best.minute.ok_or("best guard never fell asleep")?;
+    Ok(best.guard * minute)
+}
+
+/// Builds up sleep records from a time-ordered stream of events, one event at a time.
+#[derive(Debug, Default)]
+struct GuardLog {
+    guard_to_sleep_record: HashMap<usize, SleepRecord>,
+    current_guard: usize,
+    /// When the current guard fell asleep, if they're asleep.
+    sleep_start_time: Option<usize>,
+}
+
+impl GuardLog {
+    /// Fails on a wake-up with no fall-asleep earlier in the same shift, or one that's earlier
+    /// than the fall-asleep it ends.
+    fn record(&mut self, event: Event) -> Result<()> {
         match event {
-            Event::BeginsShift { guard_id } => current_guard = guard_id,
-            Event::FallsAsleep { sleep_time } => sleep_start_time = sleep_time,
+            Event::BeginsShift { guard_id } => {
+                self.current_guard = guard_id;
+                self.sleep_start_time = None;
+                self.guard_to_sleep_record
+                    .entry(guard_id)
+                    .or_default()
+                    .nightly_minutes_asleep
+                    .push(0);
+            }
+            Event::FallsAsleep { sleep_time } => self.sleep_start_time = Some(sleep_time),
             Event::WakesUp { wake_time } => {
-                let histogram = guard_to_sleep_histogram
-                    .entry(current_guard)
-                    .or_insert_with(|| [0; 60]);
-                for minute in sleep_start_time..wake_time {
-                    histogram[minute] += 1;
+                let sleep_time = self.sleep_start_time.take().ok_or_else(|| {
+                    format!(
+                        "guard #{} woke up at minute {} without falling asleep",
+                        self.current_guard, wake_time
+                    )
+                })?;
+                if wake_time < sleep_time {
+                    return Err(From::from(format!(
+                        "guard #{} woke up at minute {} before falling asleep at minute {}",
+                        self.current_guard, wake_time, sleep_time
+                    )));
                 }
+                self.guard_to_sleep_record
+                    .entry(self.current_guard)
+                    .or_default()
+                    .record_nap(sleep_time, wake_time);
             }
         }
+        Ok(())
+    }
+
+    fn into_sleep_records(self) -> HashMap<usize, SleepRecord> {
+        self.guard_to_sleep_record
     }
-    guard_to_sleep_histogram
 }
 
 fn parse_event(s: &str) -> Result<Event> {
@@ -110,3 +255,18 @@ fn parse_event(s: &str) -> Result<Event> {
         Err(From::from(format!("Can't parse message: {}", msg)))
     }
 }
+
+#[test]
+fn test_wake_without_sleep() {
+    let mut guard_log = GuardLog::default();
+    guard_log
+        .record(Event::BeginsShift { guard_id: 10 })
+        .unwrap();
+    guard_log
+        .record(Event::FallsAsleep { sleep_time: 40 })
+        .unwrap();
+    guard_log
+        .record(Event::BeginsShift { guard_id: 99 })
+        .unwrap();
+    assert!(guard_log.record(Event::WakesUp { wake_time: 10 }).is_err());
+}
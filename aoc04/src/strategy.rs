@@ -0,0 +1,163 @@
+use crate::SleepRecord;
+use std::collections::HashMap;
+
+/// A way of choosing which guard to sneak past, and at which minute.
+pub trait Strategy {
+    fn name(&self) -> &'static str;
+
+    /// Scores a single guard. Guards with higher scores rank first.
+    fn score(&self, record: &SleepRecord) -> f64;
+
+    /// The minute to sneak past the guard, or `None` if they never slept.
+    fn minute(&self, record: &SleepRecord) -> Option<usize> {
+        record.minute_asleep_most()
+    }
+}
+
+#[derive(Debug)]
+pub struct Ranking {
+    pub guard: usize,
+    pub score: f64,
+    pub minute: Option<usize>,
+}
+
+/// The guard with the most total minutes asleep (the puzzle's part 1).
+pub struct MostMinutesAsleep;
+/// The guard most frequently asleep on the same minute (the puzzle's part 2).
+pub struct MostFrequentMinute;
+/// The guard who fell asleep on the most distinct nights.
+pub struct MostNightsAsleep;
+/// The guard who took the longest single nap.
+pub struct LongestNap;
+/// The guard who is asleep at their favourite minute on the largest fraction of shifts.
+pub struct MostConsistentSleeper;
+
+impl Strategy for MostMinutesAsleep {
+    fn name(&self) -> &'static str {
+        "most-minutes"
+    }
+
+    fn score(&self, record: &SleepRecord) -> f64 {
+        record.total_minutes_asleep() as f64
+    }
+}
+
+impl Strategy for MostFrequentMinute {
+    fn name(&self) -> &'static str {
+        "most-frequent-minute"
+    }
+
+    fn score(&self, record: &SleepRecord) -> f64 {
+        record
+            .minute_asleep_most()
+            .map(|minute| record.sleep_histogram[minute])
+            .unwrap_or(0) as f64
+    }
+}
+
+impl Strategy for MostNightsAsleep {
+    fn name(&self) -> &'static str {
+        "most-nights"
+    }
+
+    fn score(&self, record: &SleepRecord) -> f64 {
+        record
+            .nightly_minutes_asleep
+            .iter()
+            .filter(|&&minutes| minutes > 0)
+            .count() as f64
+    }
+}
+
+impl Strategy for LongestNap {
+    fn name(&self) -> &'static str {
+        "longest-nap"
+    }
+
+    fn score(&self, record: &SleepRecord) -> f64 {
+        record.longest_nap as f64
+    }
+}
+
+impl Strategy for MostConsistentSleeper {
+    fn name(&self) -> &'static str {
+        "most-consistent"
+    }
+
+    fn score(&self, record: &SleepRecord) -> f64 {
+        let shifts = record.nightly_minutes_asleep.len();
+        match record.minute_asleep_most() {
+            Some(minute) if shifts > 0 => record.sleep_histogram[minute] as f64 / shifts as f64,
+            _ => 0.0,
+        }
+    }
+}
+
+pub fn all_strategies() -> Vec<Box<dyn Strategy>> {
+    vec![
+        Box::new(MostMinutesAsleep),
+        Box::new(MostFrequentMinute),
+        Box::new(MostNightsAsleep),
+        Box::new(LongestNap),
+        Box::new(MostConsistentSleeper),
+    ]
+}
+
+pub fn strategy_by_name(name: &str) -> Option<Box<dyn Strategy>> {
+    all_strategies()
+        .into_iter()
+        .find(|strategy| strategy.name() == name)
+}
+
+/// Ranks every guard by the strategy's score, best first. Ties are broken by guard id.
+pub fn rank(
+    strategy: &dyn Strategy,
+    guard_to_record: &HashMap<usize, SleepRecord>,
+) -> Vec<Ranking> {
+    let mut rankings: Vec<Ranking> = guard_to_record
+        .iter()
+        .map(|(&guard, record)| Ranking {
+            guard,
+            score: strategy.score(record),
+            minute: strategy.minute(record),
+        })
+        .collect();
+    rankings.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.guard.cmp(&b.guard))
+    });
+    rankings
+}
+
+pub fn format_rankings(strategy: &dyn Strategy, rankings: &[Ranking]) -> String {
+    let mut table = format!("Strategy: {}\n", strategy.name());
+    table.push_str(&format!(
+        "{:>4}  {:>6}  {:>8}  {:>6}  {:>8}\n",
+        "Rank", "Guard", "Score", "Minute", "Product"
+    ));
+    for (idx, ranking) in rankings.iter().enumerate() {
+        let (minute, product) = match ranking.minute {
+            Some(minute) => (minute.to_string(), (minute * ranking.guard).to_string()),
+            None => ("-".to_string(), "-".to_string()),
+        };
+        table.push_str(&format!(
+            "{:>4}  {:>6}  {:>8}  {:>6}  {:>8}\n",
+            idx + 1,
+            ranking.guard,
+            format_score(ranking.score),
+            minute,
+            product
+        ));
+    }
+    table
+}
+
+fn format_score(score: f64) -> String {
+    if score.fract() == 0.0 {
+        format!("{}", score)
+    } else {
+        format!("{:.3}", score)
+    }
+}
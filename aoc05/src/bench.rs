@@ -0,0 +1,71 @@
+use crate::parallel;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_LEN: usize = 1_000_000;
+
+/// Times `reduce` and `part2` on generated polymers that are worst cases for a reducer that
+/// repeatedly scans for adjacent pairs.
+pub fn run(len: usize, threads: usize) {
+    let polymers: Vec<(&str, Vec<u8>)> = vec![
+        ("nested (aaa...AAA)", nested(len)),
+        ("blocked (aaa...bAAA...)", blocked(len)),
+        ("alternating (aAaA...)", alternating(len)),
+        ("inert (abcd...)", inert(len)),
+    ];
+    println!("{} threads", threads);
+    println!(
+        "{:<24} {:>10} {:>12} {:>12} {:>12} {:>12}",
+        "polymer", "units", "reduce", "par_reduce", "part2", "par_part2"
+    );
+    for (name, polymer) in polymers {
+        let (reduced, reduce_time) = time(|| crate::reduce(&polymer));
+        let (par_reduced, par_reduce_time) = time(|| parallel::par_reduce(&polymer, threads));
+        assert_eq!(reduced, par_reduced);
+        let (part2, part2_time) = time(|| crate::part2(&reduced).unwrap());
+        let (par_part2, par_part2_time) = time(|| parallel::par_part2(&reduced, threads).unwrap());
+        assert_eq!(part2, par_part2);
+        println!(
+            "{:<24} {:>10} {:>12?} {:>12?} {:>12?} {:>12?}",
+            name,
+            polymer.len(),
+            reduce_time,
+            par_reduce_time,
+            part2_time,
+            par_part2_time
+        );
+    }
+}
+
+fn time<T, F: FnOnce() -> T>(f: F) -> (T, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+/// `aaa...AAA`: every reaction happens at the seam in the middle, one pair per pass.
+fn nested(len: usize) -> Vec<u8> {
+    let half = len / 2;
+    std::iter::repeat_n(b'a', half)
+        .chain(std::iter::repeat_n(b'A', half))
+        .collect()
+}
+
+/// `aaa...bAAA...`: inert as given, but removing `b` makes the whole polymer collapse from the
+/// middle.
+fn blocked(len: usize) -> Vec<u8> {
+    let half = len / 2;
+    std::iter::repeat_n(b'a', half)
+        .chain(std::iter::once(b'b'))
+        .chain(std::iter::repeat_n(b'A', half))
+        .collect()
+}
+
+/// `aAaA...`: everything annihilates in one pass.
+fn alternating(len: usize) -> Vec<u8> {
+    [b'a', b'A'].iter().cycle().take(len).cloned().collect()
+}
+
+/// `abcd...`: nothing reacts.
+fn inert(len: usize) -> Vec<u8> {
+    (b'a'..=b'z').cycle().take(len).collect()
+}
@@ -1,61 +1,184 @@
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
+mod bench;
+mod optimize;
+mod parallel;
+mod rules;
+mod stream;
+mod trace;
+
 fn main() -> Result<()> {
-    let polymer: Vec<char> = std::fs::read_to_string("input")?.trim().chars().collect();
-    let reduced_polymer = reduce(polymer)?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("bench") {
+        let len = match args.get(1) {
+            Some(len) => len.parse()?,
+            None => bench::DEFAULT_LEN,
+        };
+        let threads = match args.get(2) {
+            Some(threads) => threads.parse()?,
+            None => parallel::available_threads(),
+        };
+        bench::run(len, threads);
+        return Ok(());
+    }
+
+    if args.first().map(String::as_str) == Some("optimize") {
+        return run_optimize(&args[1..]);
+    }
+    if args.first().map(String::as_str) == Some("stream") {
+        return run_stream(&args[1..]);
+    }
+    if args.first().map(String::as_str) == Some("trace") {
+        return run_trace(&args[1..]);
+    }
+
+    let input = std::fs::read_to_string("input")?;
+    if let Some(idx) = args.iter().position(|arg| arg == "--rules") {
+        let path = args.get(idx + 1).ok_or("--rules requires a file")?;
+        let rules = match path.as_str() {
+            "default" => rules::RuleTable::default(),
+            path => rules::RuleTable::from_file(path)?,
+        };
+        let polymer: Vec<char> = input.trim().chars().collect();
+        println!("Part1: {}", rules::reduce_with(&rules, &polymer).len());
+        println!("Part2: {}", rules::part2_with(&rules, &polymer)?);
+        return Ok(());
+    }
+
+    let polymer = parse_polymer(&input)?;
+    let threads = parallel::available_threads();
+    let reduced_polymer = parallel::par_reduce(&polymer, threads);
     println!("Part1: {}", reduced_polymer.len());
-    println!("Part2: {}", part2(reduced_polymer)?);
+    println!("Part2: {}", parallel::par_part2(&reduced_polymer, threads)?);
     Ok(())
 }
 
-fn part2(polymer: Vec<char>) -> Result<usize> {
-    (b'a'..=b'z')
-        .map(|c| {
-            polymer
-                .iter()
-                .filter(|unit| unit.to_lowercase().next().unwrap() != c as char)
-                .map(|&c| c)
-                .collect::<Vec<char>>()
-        })
-        .map(|filtered_polymer| reduce(filtered_polymer).unwrap().len())
-        .min()
-        .ok_or(From::from("no polymers"))
+/// usage: aoc05 optimize [<max unit types removed>] [--beam <width>]
+fn run_optimize(args: &[String]) -> Result<()> {
+    let mut max_removed = 2;
+    let mut beam_width = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--beam" => beam_width = Some(args.next().ok_or("--beam requires a width")?.parse()?),
+            k => max_removed = k.parse()?,
+        }
+    }
+    let polymer = parse_polymer(&std::fs::read_to_string("input")?)?;
+    let removals = optimize::search(&reduce(&polymer), max_removed, beam_width);
+    let best = removals.first().ok_or("no unit types to remove")?;
+    println!("Best: remove {} -> {} units", best.unit_types, best.len);
+    for removal in &removals {
+        println!("{:>26} {:>8}", removal.unit_types, removal.len);
+    }
+    Ok(())
 }
 
-fn reduce(mut polymer: Vec<char>) -> Result<Vec<char>> {
-    let mut previous_len;
-    loop {
-        previous_len = polymer.len();
-        polymer = reduce_step(polymer)?;
-        if polymer.len() == previous_len {
-            break Ok(polymer);
+/// usage: aoc05 stream [<file>|-] [--max-memory <units>]
+fn run_stream(args: &[String]) -> Result<()> {
+    let mut path = "input".to_string();
+    let mut max_in_memory = stream::DEFAULT_MAX_IN_MEMORY;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--max-memory" => {
+                max_in_memory = args
+                    .next()
+                    .ok_or("--max-memory requires a value")?
+                    .parse()?
+            }
+            other => path = other.to_string(),
         }
     }
+    let mut reducer = stream::StreamReducer::new(max_in_memory);
+    if path == "-" {
+        reducer.feed(std::io::stdin().lock())?;
+    } else {
+        reducer.feed(std::fs::File::open(&path)?)?;
+    }
+    let mut reduced = reducer.finish();
+    println!("Part1: {}", reduced.len());
+    println!("Part2: {}", stream::part2(&mut reduced, max_in_memory)?);
+    Ok(())
 }
 
-fn reduce_step(mut polymer: Vec<char>) -> Result<Vec<char>> {
-    let mut insert_idx = 0;
-    let mut curr_idx = 0;
-    while curr_idx < polymer.len() {
-        if curr_idx + 1 < polymer.len() && will_react(polymer[curr_idx], polymer[curr_idx + 1])? {
-            // they get destroyed
-            curr_idx += 2;
+const EXAMPLE: &str = "dabAcCaCBAcCcaDA";
+
+/// usage: aoc05 trace [example|<file>] [--replay]
+fn run_trace(args: &[String]) -> Result<()> {
+    let input = match args.iter().find(|arg| !arg.starts_with("--")) {
+        Some(source) if source == "example" => EXAMPLE.to_string(),
+        Some(path) => std::fs::read_to_string(path)?,
+        None => std::fs::read_to_string("input")?,
+    };
+    let polymer = parse_polymer(&input)?;
+    let trace = trace::reduce_traced(&polymer);
+    if args.iter().any(|arg| arg == "--replay") {
+        println!("{}", String::from_utf8_lossy(&polymer));
+        for (annihilation, state) in trace.annihilations.iter().zip(trace.replay(&polymer)) {
+            println!("{}\n  {}", annihilation, state);
+        }
+    } else {
+        for annihilation in &trace.annihilations {
+            println!("{}", annihilation);
+        }
+    }
+    let reduced_len = reduce(&polymer).len();
+    println!(
+        "{} annihilations, max depth {}, {} units left (reduce: {}{})",
+        trace.annihilations.len(),
+        trace.max_depth().map_or("-".to_string(), |d| d.to_string()),
+        trace.survivors.len(),
+        reduced_len,
+        if reduced_len == trace.survivors.len() {
+            ""
         } else {
-            polymer[insert_idx] = polymer[curr_idx];
-            insert_idx += 1;
-            curr_idx += 1;
+            ", MISMATCH"
         }
+    );
+    Ok(())
+}
+
+fn parse_polymer(s: &str) -> Result<Vec<u8>> {
+    let polymer = s.trim().as_bytes();
+    match polymer.iter().find(|unit| !unit.is_ascii_alphabetic()) {
+        Some(&unit) => Err(From::from(format!("invalid unit: {:?}", unit as char))),
+        None => Ok(polymer.to_vec()),
     }
-    polymer.truncate(insert_idx);
-    Ok(polymer)
 }
 
-fn will_react(first: char, second: char) -> Result<bool> {
-    if first.is_lowercase() {
-        Ok(second.is_uppercase()
-            && first == second.to_lowercase().next().ok_or("invalid character")?)
-    } else {
-        Ok(second.is_lowercase()
-            && first.to_lowercase().next().ok_or("invalid character")? == second)
+/// Removing a unit type commutes with reduction, so each variant can start from the already
+/// reduced polymer rather than the original input.
+fn part2(reduced_polymer: &[u8]) -> Result<usize> {
+    (b'a'..=b'z')
+        .map(|unit_type| reduce(&remove_unit_type(reduced_polymer, unit_type)).len())
+        .min()
+        .ok_or_else(|| From::from("no polymers"))
+}
+
+fn remove_unit_type(polymer: &[u8], unit_type: u8) -> Vec<u8> {
+    polymer
+        .iter()
+        .filter(|unit| unit.to_ascii_lowercase() != unit_type)
+        .cloned()
+        .collect()
+}
+
+/// Fully reduces the polymer in a single pass. The units that have survived so far are kept on a
+/// stack; each new unit either annihilates with the top of the stack or is pushed onto it.
+fn reduce(polymer: &[u8]) -> Vec<u8> {
+    let mut stack = Vec::with_capacity(polymer.len());
+    for &unit in polymer {
+        match stack.last() {
+            Some(&top) if will_react(top, unit) => {
+                stack.pop();
+            }
+            _ => stack.push(unit),
+        }
     }
+    stack
+}
+
+fn will_react(first: u8, second: u8) -> bool {
+    first != second && first.eq_ignore_ascii_case(&second)
 }
@@ -0,0 +1,83 @@
+use crate::{reduce, remove_unit_type};
+use std::collections::HashMap;
+
+/// Unit types as a bitmask, bit 0 for `a` through bit 25 for `z`.
+type UnitTypeSet = u32;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Removal {
+    /// The removed unit types, in alphabetical order.
+    pub unit_types: String,
+    pub len: usize,
+}
+
+/// Generalizes `part2` to removing up to `max_removed` unit types at once. Returns the reduced
+/// length for every set of unit types evaluated, shortest first.
+///
+/// Sets are built up one unit type at a time, and each one is reduced starting from the reduced
+/// polymer of the set it extends, since removal commutes with reduction. Two prunings keep the
+/// search tractable:
+/// - a unit type that no longer appears in the reduced polymer is never added, since removing it
+///   would give the same polymer as the smaller set;
+/// - with a `beam_width`, only that many of the shortest sets of each size are extended further.
+///   Without one the search is exhaustive.
+pub fn search(
+    reduced_polymer: &[u8],
+    max_removed: usize,
+    beam_width: Option<usize>,
+) -> Vec<Removal> {
+    let mut table: HashMap<UnitTypeSet, usize> = HashMap::new();
+    let mut frontier: Vec<(UnitTypeSet, Vec<u8>)> = vec![(0, reduced_polymer.to_vec())];
+
+    for size in 1..=max_removed {
+        if let Some(beam_width) = beam_width {
+            frontier.sort_by_key(|(set, polymer)| (polymer.len(), *set));
+            frontier.truncate(beam_width);
+        }
+        let is_last_level = size == max_removed;
+        let mut next_frontier: HashMap<UnitTypeSet, Vec<u8>> = HashMap::new();
+        for (set, polymer) in &frontier {
+            for unit_type in unit_types_in(present_unit_types(polymer) & !set) {
+                let child = set | (1 << unit_type);
+                if table.contains_key(&child) {
+                    continue;
+                }
+                let reduced = reduce(&remove_unit_type(polymer, b'a' + unit_type));
+                table.insert(child, reduced.len());
+                // the last level is never extended, so don't hold on to its polymers
+                if !is_last_level {
+                    next_frontier.insert(child, reduced);
+                }
+            }
+        }
+        frontier = next_frontier.into_iter().collect();
+    }
+
+    let mut removals: Vec<Removal> = table
+        .into_iter()
+        .map(|(set, len)| Removal {
+            unit_types: set_to_string(set),
+            len,
+        })
+        .collect();
+    removals.sort_by(|a, b| {
+        (a.len, a.unit_types.len(), &a.unit_types).cmp(&(b.len, b.unit_types.len(), &b.unit_types))
+    });
+    removals
+}
+
+fn present_unit_types(polymer: &[u8]) -> UnitTypeSet {
+    polymer.iter().fold(0, |set, unit| {
+        set | (1 << (unit.to_ascii_lowercase() - b'a'))
+    })
+}
+
+fn unit_types_in(set: UnitTypeSet) -> impl Iterator<Item = u8> {
+    (0..26).filter(move |unit_type| set & (1 << unit_type) != 0)
+}
+
+fn set_to_string(set: UnitTypeSet) -> String {
+    unit_types_in(set)
+        .map(|unit_type| (b'a' + unit_type) as char)
+        .collect()
+}
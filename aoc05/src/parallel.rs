@@ -0,0 +1,109 @@
+use crate::{reduce, remove_unit_type, will_react, Result};
+
+/// Below this many units per thread it's faster to reduce on a single thread.
+#[cfg(not(test))]
+const MIN_CHUNK_LEN: usize = 1 << 16;
+/// Small enough for tests to split short polymers.
+#[cfg(test)]
+const MIN_CHUNK_LEN: usize = 4;
+
+pub fn available_threads() -> usize {
+    std::thread::available_parallelism()
+        .map(|threads| threads.get())
+        .unwrap_or(1)
+}
+
+/// Reduces the polymer by splitting it into chunks, reducing each chunk on its own thread and
+/// then merging the reduced chunks left to right.
+///
+/// Reduction is associative: a reduced chunk can only react with its neighbours at the seam,
+/// where the unmatched units at the end of the left chunk meet those at the start of the right
+/// one, so merging two reduced chunks is a single cancellation pass over the seam.
+pub fn par_reduce(polymer: &[u8], threads: usize) -> Vec<u8> {
+    let threads = threads.clamp(1, std::cmp::max(polymer.len() / MIN_CHUNK_LEN, 1));
+    if threads == 1 {
+        return reduce(polymer);
+    }
+    let chunk_len = polymer.len().div_ceil(threads);
+    let reduced_chunks: Vec<Vec<u8>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = polymer
+            .chunks(chunk_len)
+            .map(|chunk| scope.spawn(move || reduce(chunk)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("reducer thread panicked"))
+            .collect()
+    });
+    reduced_chunks.into_iter().reduce(merge).unwrap_or_default()
+}
+
+/// Joins two reduced polymers, cancelling the units that react across the seam.
+pub fn merge(mut left: Vec<u8>, right: Vec<u8>) -> Vec<u8> {
+    let mut seam = 0;
+    while seam < right.len() {
+        match left.last() {
+            Some(&last) if will_react(last, right[seam]) => {
+                left.pop();
+                seam += 1;
+            }
+            _ => break,
+        }
+    }
+    left.extend_from_slice(&right[seam..]);
+    left
+}
+
+/// `part2` with the 26 unit-removal variants spread across `threads` threads.
+pub fn par_part2(reduced_polymer: &[u8], threads: usize) -> Result<usize> {
+    let unit_types: Vec<u8> = (b'a'..=b'z').collect();
+    let per_thread = unit_types.len().div_ceil(threads.max(1));
+    let lengths: Vec<usize> = std::thread::scope(|scope| {
+        let handles: Vec<_> = unit_types
+            .chunks(per_thread)
+            .map(|unit_types| {
+                scope.spawn(move || {
+                    unit_types
+                        .iter()
+                        .map(|&unit_type| {
+                            reduce(&remove_unit_type(reduced_polymer, unit_type)).len()
+                        })
+                        .collect::<Vec<usize>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("part2 thread panicked"))
+            .collect()
+    });
+    lengths
+        .into_iter()
+        .min()
+        .ok_or_else(|| From::from("no polymers"))
+}
+
+#[test]
+fn test_merge() {
+    assert_eq!(merge(b"dabc".to_vec(), b"CBAe".to_vec()), b"de");
+    assert_eq!(merge(b"ab".to_vec(), b"BAa".to_vec()), b"a");
+    assert_eq!(merge(b"aB".to_vec(), b"c".to_vec()), b"aBc");
+}
+
+#[test]
+fn test_par_reduce_matches_reduce() {
+    // pseudo-random units from a few types, so reactions often span the seams between chunks
+    let mut state: u32 = 1;
+    let polymer: Vec<u8> = (0..1000)
+        .map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            b"aAbBc"[(state >> 16) as usize % 5]
+        })
+        .collect();
+    let nested = [b"abcde".repeat(20), b"EDCBA".repeat(20)].concat();
+    for polymer in &[polymer, nested, b"dabAcCaCBAcCcaDA".to_vec()] {
+        for threads in 1..=8 {
+            assert_eq!(par_reduce(polymer, threads), reduce(polymer));
+        }
+    }
+}
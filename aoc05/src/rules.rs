@@ -0,0 +1,142 @@
+use crate::Result;
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Reaction {
+    /// Both units are destroyed.
+    Annihilate,
+    /// Both units are replaced by a single new unit, which may go on to react with its neighbours.
+    Replace(char),
+}
+
+/// Which adjacent pairs of units react, and what they turn into.
+///
+/// A rule table file has one rule per line. `a b` means that `a` immediately followed by `b`
+/// annihilate; `a b -> c` means that they are replaced by `c`. Rules only apply in the order
+/// given, so a symmetric reaction needs both `a b` and `b a`. Units are any single character
+/// other than whitespace. Blank lines and lines starting with `#` are ignored.
+#[derive(Debug, Clone)]
+pub struct RuleTable {
+    reactions: HashMap<(char, char), Reaction>,
+}
+
+impl Default for RuleTable {
+    /// The puzzle's rules: the same letter in opposite cases annihilate.
+    fn default() -> Self {
+        let mut reactions = HashMap::with_capacity(52);
+        for lower in 'a'..='z' {
+            let upper = lower.to_ascii_uppercase();
+            reactions.insert((lower, upper), Reaction::Annihilate);
+            reactions.insert((upper, lower), Reaction::Annihilate);
+        }
+        RuleTable { reactions }
+    }
+}
+
+impl RuleTable {
+    pub fn from_file(path: &str) -> Result<RuleTable> {
+        RuleTable::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(s: &str) -> Result<RuleTable> {
+        let mut reactions = HashMap::new();
+        for (line_idx, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let (first, second, reaction) = match tokens.as_slice() {
+                [first, second] => (*first, *second, Reaction::Annihilate),
+                [first, second, "->", product] => (
+                    *first,
+                    *second,
+                    Reaction::Replace(parse_unit(product, line_idx)?),
+                ),
+                _ => {
+                    return Err(From::from(format!(
+                        "line {}: expected `a b` or `a b -> c`, got {:?}",
+                        line_idx + 1,
+                        line
+                    )))
+                }
+            };
+            let pair = (parse_unit(first, line_idx)?, parse_unit(second, line_idx)?);
+            if reactions.insert(pair, reaction).is_some() {
+                return Err(From::from(format!(
+                    "line {}: duplicate rule for {}{}",
+                    line_idx + 1,
+                    pair.0,
+                    pair.1
+                )));
+            }
+        }
+        Ok(RuleTable { reactions })
+    }
+
+    pub fn reaction(&self, first: char, second: char) -> Option<Reaction> {
+        self.reactions.get(&(first, second)).cloned()
+    }
+}
+
+fn parse_unit(token: &str, line_idx: usize) -> Result<char> {
+    let mut chars = token.chars();
+    match (chars.next(), chars.next()) {
+        (Some(unit), None) => Ok(unit),
+        _ => Err(From::from(format!(
+            "line {}: a unit must be a single character, got {:?}",
+            line_idx + 1,
+            token
+        ))),
+    }
+}
+
+/// Reduces the polymer under an arbitrary rule table, in a single pass over a stack of the units
+/// that have survived so far. Reactions happen as soon as a pair becomes adjacent, leftmost
+/// first; with replacement rules the result can depend on that order.
+pub fn reduce_with(rules: &RuleTable, polymer: &[char]) -> Vec<char> {
+    let mut stack = Vec::with_capacity(polymer.len());
+    for &unit in polymer {
+        let mut unit = unit;
+        loop {
+            match stack.last().and_then(|&top| rules.reaction(top, unit)) {
+                Some(Reaction::Annihilate) => {
+                    stack.pop();
+                    break;
+                }
+                Some(Reaction::Replace(product)) => {
+                    stack.pop();
+                    unit = product;
+                }
+                None => {
+                    stack.push(unit);
+                    break;
+                }
+            }
+        }
+    }
+    stack
+}
+
+/// `part2` under an arbitrary rule table: removes each unit type (a unit and its other-case
+/// forms) in turn from the original polymer and reduces what remains. Replacement rules mean
+/// removal no longer commutes with reduction, so each variant starts from scratch.
+pub fn part2_with(rules: &RuleTable, polymer: &[char]) -> Result<usize> {
+    let unit_types: BTreeSet<char> = polymer.iter().map(|&unit| unit_type(unit)).collect();
+    unit_types
+        .into_iter()
+        .map(|removed| {
+            let filtered: Vec<char> = polymer
+                .iter()
+                .filter(|&&unit| unit_type(unit) != removed)
+                .cloned()
+                .collect();
+            reduce_with(rules, &filtered).len()
+        })
+        .min()
+        .ok_or_else(|| From::from("no polymers"))
+}
+
+fn unit_type(unit: char) -> char {
+    unit.to_lowercase().next().unwrap_or(unit)
+}
@@ -0,0 +1,208 @@
+use crate::{will_react, Result};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub const DEFAULT_MAX_IN_MEMORY: usize = 64 << 20;
+const READ_CHUNK_LEN: usize = 64 << 10;
+
+static SPILL_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Reduces a polymer as it is read, keeping only the stack of units that have not (yet) reacted.
+/// Once the stack grows past `max_in_memory` units its bottom half is spilled to a temporary
+/// file, and read back when the in-memory part is used up.
+pub struct StreamReducer {
+    stack: Vec<u8>,
+    max_in_memory: usize,
+    spill: Option<Spill>,
+}
+
+/// The fully reduced polymer: the spilled bottom of the stack followed by the in-memory top.
+pub struct Reduced {
+    stack: Vec<u8>,
+    spill: Option<Spill>,
+}
+
+/// A temporary file holding the bottom of a stack. It is removed on drop.
+struct Spill {
+    file: File,
+    path: PathBuf,
+    len: u64,
+}
+
+impl StreamReducer {
+    pub fn new(max_in_memory: usize) -> StreamReducer {
+        let max_in_memory = std::cmp::max(max_in_memory, 2);
+        StreamReducer {
+            stack: Vec::with_capacity(std::cmp::min(max_in_memory, READ_CHUNK_LEN)),
+            max_in_memory,
+            spill: None,
+        }
+    }
+
+    /// Reads the polymer from `reader` in chunks, ignoring whitespace.
+    pub fn feed<R: Read>(&mut self, reader: R) -> Result<()> {
+        self.feed_where(reader, |_unit| true)
+    }
+
+    /// Like `feed`, but skips any unit for which `keep` returns false.
+    pub fn feed_where<R, F>(&mut self, mut reader: R, keep: F) -> Result<()>
+    where
+        R: Read,
+        F: Fn(u8) -> bool,
+    {
+        let mut buf = vec![0; READ_CHUNK_LEN];
+        loop {
+            let bytes_read = match reader.read(&mut buf) {
+                Ok(0) => return Ok(()),
+                Ok(bytes_read) => bytes_read,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(From::from(e)),
+            };
+            for &unit in &buf[..bytes_read] {
+                if unit.is_ascii_whitespace() || !keep(unit) {
+                    continue;
+                }
+                if !unit.is_ascii_alphabetic() {
+                    return Err(From::from(format!("invalid unit: {:?}", unit as char)));
+                }
+                self.push(unit)?;
+            }
+        }
+    }
+
+    pub fn push(&mut self, unit: u8) -> Result<()> {
+        if self.stack.is_empty() {
+            self.refill()?;
+        }
+        match self.stack.last() {
+            Some(&top) if will_react(top, unit) => {
+                self.stack.pop();
+            }
+            _ => {
+                self.stack.push(unit);
+                if self.stack.len() > self.max_in_memory {
+                    self.spill_bottom_half()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of units left so far.
+    pub fn len(&self) -> u64 {
+        self.stack.len() as u64 + self.spill.as_ref().map_or(0, |spill| spill.len)
+    }
+
+    pub fn finish(self) -> Reduced {
+        Reduced {
+            stack: self.stack,
+            spill: self.spill,
+        }
+    }
+
+    fn spill_bottom_half(&mut self) -> Result<()> {
+        if self.spill.is_none() {
+            self.spill = Some(Spill::create()?);
+        }
+        let spill = self.spill.as_mut().unwrap();
+        let half = self.stack.len() / 2;
+        spill.file.seek(SeekFrom::Start(spill.len))?;
+        spill.file.write_all(&self.stack[..half])?;
+        spill.len += half as u64;
+        self.stack.drain(..half);
+        Ok(())
+    }
+
+    fn refill(&mut self) -> Result<()> {
+        let spill = match &mut self.spill {
+            Some(spill) if spill.len > 0 => spill,
+            _ => return Ok(()),
+        };
+        let refill_len = std::cmp::min(spill.len, (self.max_in_memory / 2) as u64);
+        let start = spill.len - refill_len;
+        spill.file.seek(SeekFrom::Start(start))?;
+        self.stack.resize(refill_len as usize, 0);
+        spill.file.read_exact(&mut self.stack)?;
+        spill.file.set_len(start)?;
+        spill.len = start;
+        Ok(())
+    }
+}
+
+impl Spill {
+    fn create() -> Result<Spill> {
+        let path = std::env::temp_dir().join(format!(
+            "aoc05-spill-{}-{}",
+            std::process::id(),
+            SPILL_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        Ok(Spill { file, path, len: 0 })
+    }
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+impl Reduced {
+    pub fn len(&self) -> u64 {
+        self.stack.len() as u64 + self.spill.as_ref().map_or(0, |spill| spill.len)
+    }
+
+    /// Streams the reduced polymer from the start.
+    pub fn reader(&mut self) -> Result<impl Read + '_> {
+        let spilled: Box<dyn Read + '_> = match &mut self.spill {
+            Some(spill) => {
+                spill.file.seek(SeekFrom::Start(0))?;
+                Box::new((&spill.file).take(spill.len))
+            }
+            None => Box::new(std::io::empty()),
+        };
+        Ok(spilled.chain(&self.stack[..]))
+    }
+}
+
+/// `part2` over a reduced polymer that may not fit in memory: each unit-removal variant is
+/// reduced by streaming the reduced polymer through a fresh reducer.
+pub fn part2(reduced: &mut Reduced, max_in_memory: usize) -> Result<u64> {
+    let mut shortest = None;
+    for unit_type in b'a'..=b'z' {
+        let mut reducer = StreamReducer::new(max_in_memory);
+        reducer.feed_where(reduced.reader()?, |unit| {
+            unit.to_ascii_lowercase() != unit_type
+        })?;
+        let len = reducer.len();
+        shortest = Some(shortest.map_or(len, |shortest: u64| shortest.min(len)));
+    }
+    shortest.ok_or_else(|| From::from("no polymers"))
+}
+
+#[test]
+fn test_stream_reducer_spills_and_refills() {
+    // grows the stack to 100 units, far past what fits in memory, then reacts it all away
+    let nested = [b"abcde".repeat(20), b"EDCBA".repeat(20)].concat();
+    let polymers = [
+        nested.clone(),
+        [b"x".to_vec(), nested, b"Yz".to_vec()].concat(),
+        [b"a".repeat(50), b"A".repeat(50)].concat(),
+        b"dabAcCaCBAcCcaDA".to_vec(),
+    ];
+    for polymer in &polymers {
+        let mut reducer = StreamReducer::new(4);
+        reducer.feed(&polymer[..]).unwrap();
+        let mut reduced = reducer.finish();
+        let mut units = Vec::new();
+        reduced.reader().unwrap().read_to_end(&mut units).unwrap();
+        assert_eq!(units, crate::reduce(polymer));
+        assert_eq!(reduced.len(), units.len() as u64);
+    }
+}
@@ -0,0 +1,115 @@
+use crate::will_react;
+
+/// A single reaction: two units that annihilated each other.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Annihilation {
+    /// Position of this reaction in the order the reducer performed them.
+    pub step: usize,
+    pub left_unit: u8,
+    /// Index of the left unit in the original polymer.
+    pub left_pos: usize,
+    pub right_unit: u8,
+    pub right_pos: usize,
+    /// 0 if the two units were adjacent in the original polymer, otherwise one more than the
+    /// deepest reaction that had to happen between them first.
+    pub depth: usize,
+}
+
+impl std::fmt::Display for Annihilation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "step {}: {}@{} + {}@{} (depth {})",
+            self.step + 1,
+            self.left_unit as char,
+            self.left_pos,
+            self.right_unit as char,
+            self.right_pos,
+            self.depth
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct Trace {
+    pub annihilations: Vec<Annihilation>,
+    /// Original indices of the units that survive reduction.
+    pub survivors: Vec<usize>,
+}
+
+struct StackEntry {
+    unit: u8,
+    pos: usize,
+    /// Deepest reaction between this unit and the current position, if any.
+    inner_depth: Option<usize>,
+}
+
+/// Reduces the polymer like `reduce`, recording every annihilation along the way.
+pub fn reduce_traced(polymer: &[u8]) -> Trace {
+    let mut stack: Vec<StackEntry> = Vec::with_capacity(polymer.len());
+    let mut annihilations = Vec::new();
+    for (pos, &unit) in polymer.iter().enumerate() {
+        match stack.last() {
+            Some(top) if will_react(top.unit, unit) => {
+                let top = stack.pop().unwrap();
+                let depth = top.inner_depth.map_or(0, |inner_depth| inner_depth + 1);
+                annihilations.push(Annihilation {
+                    step: annihilations.len(),
+                    left_unit: top.unit,
+                    left_pos: top.pos,
+                    right_unit: unit,
+                    right_pos: pos,
+                    depth,
+                });
+                if let Some(outer) = stack.last_mut() {
+                    outer.inner_depth = Some(outer.inner_depth.map_or(depth, |d| d.max(depth)));
+                }
+            }
+            _ => stack.push(StackEntry {
+                unit,
+                pos,
+                inner_depth: None,
+            }),
+        }
+    }
+    Trace {
+        annihilations,
+        survivors: stack.into_iter().map(|entry| entry.pos).collect(),
+    }
+}
+
+impl Trace {
+    pub fn max_depth(&self) -> Option<usize> {
+        self.annihilations.iter().map(|a| a.depth).max()
+    }
+
+    /// The polymer as text after each annihilation, in order.
+    pub fn replay<'a>(&'a self, polymer: &'a [u8]) -> impl Iterator<Item = String> + 'a {
+        let mut alive = vec![true; polymer.len()];
+        self.annihilations.iter().map(move |annihilation| {
+            alive[annihilation.left_pos] = false;
+            alive[annihilation.right_pos] = false;
+            polymer
+                .iter()
+                .zip(alive.iter())
+                .filter(|(_unit, &alive)| alive)
+                .map(|(&unit, _alive)| unit as char)
+                .collect()
+        })
+    }
+}
+
+#[test]
+fn test_example_trace() {
+    let polymer = b"dabAcCaCBAcCcaDA";
+    let trace = reduce_traced(polymer);
+    let reactions: Vec<(usize, usize, usize)> = trace
+        .annihilations
+        .iter()
+        .map(|a| (a.left_pos, a.right_pos, a.depth))
+        .collect();
+    assert_eq!(reactions, [(4, 5, 0), (3, 6, 1), (10, 11, 0)]);
+    let steps: Vec<String> = trace.replay(polymer).collect();
+    assert_eq!(steps, ["dabAaCBAcCcaDA", "dabCBAcCcaDA", "dabCBAcaDA"]);
+    assert_eq!(trace.survivors.len(), crate::reduce(polymer).len());
+}
@@ -1,80 +1,166 @@
-use itertools::iproduct;
-use serde_scan::scan;
-use std::cmp::max;
-use std::collections::HashMap;
+use metric::Metric;
+use voronoi::Area;
+
+mod metric;
+mod render;
+mod safe_region;
+mod voronoi;
 
 type Result<T> = std::result::Result<T, std::boxed::Box<dyn std::error::Error>>;
-type Loc = (usize, usize);
+/// A location in any number of dimensions.
+type Loc = Vec<i64>;
 type Coord = Loc;
 
-const THRESHOLD_DIST: usize = 10000;
+const THRESHOLD_DIST: i64 = 10000;
 
 fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let metric = match args.iter().position(|arg| arg == "--metric") {
+        Some(idx) => metric::metric_by_name(args.get(idx + 1).ok_or("--metric requires a name")?)?,
+        None => Box::new(metric::Manhattan),
+    };
+
+    let threshold = match args.iter().position(|arg| arg == "--threshold") {
+        Some(idx) => args
+            .get(idx + 1)
+            .ok_or("--threshold requires a value")?
+            .parse()?,
+        None => THRESHOLD_DIST,
+    };
+
     let coords: Vec<Coord> = std::fs::read_to_string("input")?
         .lines()
         .map(parse_coordinate)
         .collect::<Result<Vec<Coord>>>()?;
 
-    let (max_x, max_y) = coords.iter().fold((0, 0), |(max_x, max_y), (x, y)| {
-        (max(max_x, *x), max(max_y, *y))
-    });
+    let dims = coords.first().map_or(0, |coord| coord.len());
+    if let Some(coord) = coords.iter().find(|coord| coord.len() != dims) {
+        return Err(From::from(format!(
+            "expected {} dimensions, found {}",
+            dims,
+            format_loc(coord)
+        )));
+    }
+    metric.check_dims(dims)?;
+
+    if args.iter().any(|arg| arg == "--areas") {
+        for (coord, area) in coords.iter().zip(areas(&coords, metric.as_ref())?) {
+            match area {
+                Area::Finite(area) => println!("{}: {}", format_loc(coord), area),
+                Area::Infinite => println!("{}: infinite", format_loc(coord)),
+            }
+        }
+    }
+
+    // every area can be infinite on inputs whose safe region is still worth counting
+    match part1(&coords, metric.as_ref()) {
+        Ok(largest_area) => println!("{}", largest_area),
+        Err(err) => eprintln!("part 1: {}", err),
+    }
+    println!("{}", part2(&coords, metric.as_ref(), threshold));
+
+    let svg_path = match args.iter().position(|arg| arg == "--svg") {
+        Some(idx) => Some(args.get(idx + 1).ok_or("--svg requires a file")?),
+        None => None,
+    };
+    let ascii = args.iter().any(|arg| arg == "--ascii");
+    if ascii || svg_path.is_some() {
+        if dims != 2 {
+            return Err(From::from("can only render 2D coordinates"));
+        }
+        let (grid, areas) = ownership(&coords, metric.as_ref())?;
+        let is_safe = |loc: &[i64]| {
+            coords
+                .iter()
+                .map(|coord| metric.dist(coord, loc))
+                .sum::<f64>()
+                < threshold as f64
+        };
+        let safe_region: Option<&render::IsSafe<'_>> =
+            if args.iter().any(|arg| arg == "--overlay-safe") {
+                Some(&is_safe)
+            } else {
+                None
+            };
+        if ascii {
+            print!("{}", render::ascii(&coords, &grid, &areas, safe_region));
+        }
+        if let Some(path) = svg_path {
+            std::fs::write(path, render::svg(&coords, &grid, &areas, safe_region))?;
+        }
+    }
 
-    println!("{}", part1(&coords, max_x, max_y)?);
-    println!("{}", part2(&coords, max_x, max_y));
+    if args.iter().any(|arg| arg == "--outline") {
+        for span in safe_region::outline(&coords, threshold) {
+            println!("{}: {}..={}", format_loc(&span.prefix), span.min, span.max);
+        }
+    }
 
     Ok(())
 }
 
-fn part2(coords: &[Coord], max_x: usize, max_y: usize) -> usize {
-    iproduct!(0..=max_x, 0..=max_y)
-        .map(|loc| coords.iter().map(|&coord| manhattan_dist(coord, loc)).sum())
-        .filter(|&total_dist: &usize| total_dist < THRESHOLD_DIST)
-        .count()
+fn part2<M: Metric + ?Sized>(coords: &[Coord], metric: &M, threshold: i64) -> usize {
+    metric.safe_region_size(coords, threshold)
 }
 
-fn part1(coords: &[Coord], max_x: usize, max_y: usize) -> Result<usize> {
-    let is_boundary = |(x, y)| x == 0 || x == max_x || y == 0 || y == max_y;
-
-    let closest_coord = |loc: Loc| {
-        let mut closest: Option<Coord> = Option::None;
-        let mut closest_dist = usize::MAX;
-        for &coord in coords {
-            let dist = manhattan_dist(coord, loc);
-            if dist == closest_dist {
-                closest = None;
-            } else if dist < closest_dist {
-                closest_dist = dist;
-                closest = Some(coord);
-            }
-        }
-        closest
-    };
-
-    let mut coord_to_area_size = HashMap::with_capacity(coords.len());
+fn part1<M: Metric + ?Sized>(coords: &[Coord], metric: &M) -> Result<usize> {
+    areas(coords, metric)?
+        .into_iter()
+        .filter_map(|area| match area {
+            Area::Finite(area) => Some(area),
+            Area::Infinite => None,
+        })
+        .max()
+        .ok_or_else(|| From::from(format!("every area is infinite under {}", metric.name())))
+}
 
-    for loc in iproduct!(0..=max_x, 0..=max_y) {
-        if let Some(coord) = closest_coord(loc) {
-            if is_boundary(loc) {
-                coord_to_area_size.remove(&coord);
-            } else {
-                let area = coord_to_area_size.entry(coord).or_insert(0);
-                *area += 1;
-            }
+/// The area of every coordinate's region under `metric`, in the same order as `coords`.
+fn areas<M: Metric + ?Sized>(coords: &[Coord], metric: &M) -> Result<Vec<Area>> {
+    check_supports_areas(coords, metric)?;
+    if let Some(infinite) = metric.known_infinite_regions(coords) {
+        if infinite.iter().all(|&infinite| infinite) {
+            return Ok(vec![Area::Infinite; coords.len()]);
         }
     }
+    Ok(ownership(coords, metric)?.1)
+}
+
+/// The ownership grid the areas are counted from, along with the areas themselves.
+fn ownership<M: Metric + ?Sized>(
+    coords: &[Coord],
+    metric: &M,
+) -> Result<(voronoi::OwnershipGrid, Vec<Area>)> {
+    check_supports_areas(coords, metric)?;
+    let grid = metric.ownership_grid(coords)?;
+    let areas = voronoi::areas(&grid, &metric.infinite_regions(coords, &grid));
+    Ok((grid, areas))
+}
 
-    coord_to_area_size
-        .iter()
-        .max_by_key(|(_k, v)| **v)
-        .map(|(_k, v)| *v)
-        .ok_or_else(|| From::from("empty coord to area map"))
+fn check_supports_areas<M: Metric + ?Sized>(coords: &[Coord], metric: &M) -> Result<()> {
+    let dims = coords.first().map_or(0, |coord| coord.len());
+    if metric.supports_areas(dims) {
+        Ok(())
+    } else {
+        Err(From::from(format!(
+            "can't find {} areas in {} dimensions",
+            metric.name(),
+            dims
+        )))
+    }
 }
 
-fn manhattan_dist((x1, y1): (usize, usize), (x2, y2): (usize, usize)) -> usize {
-    ((x1 as i64 - x2 as i64).abs() + (y1 as i64 - y2 as i64).abs()) as usize
+fn manhattan_dist(a: &[i64], b: &[i64]) -> usize {
+    a.iter().zip(b).map(|(a, b)| (a - b).abs()).sum::<i64>() as usize
 }
 
+/// Parses `x, y[, z...]`.
 fn parse_coordinate(line: &str) -> Result<Coord> {
-    let (x, y): (usize, usize) = scan!("{}, {}" <- line)?;
-    Ok((x, y))
+    line.split(',')
+        .map(|value| Ok(value.trim().parse()?))
+        .collect::<Result<Coord>>()
+}
+
+fn format_loc(loc: &[i64]) -> String {
+    let values: Vec<String> = loc.iter().map(|value| value.to_string()).collect();
+    values.join(", ")
 }
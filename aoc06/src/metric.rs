@@ -0,0 +1,346 @@
+use crate::voronoi::{self, Bounds, Owner, OwnershipGrid};
+use crate::{safe_region, Loc, Result};
+
+/// A notion of distance between locations. Distances must be at least the Chebyshev distance,
+/// so that a location can't be close to many coordinates while being far from all of them.
+pub trait Metric {
+    fn name(&self) -> String;
+
+    fn dist(&self, a: &[i64], b: &[i64]) -> f64;
+
+    /// Whether this metric can be used with locations in `dims` dimensions.
+    fn check_dims(&self, _dims: usize) -> Result<()> {
+        Ok(())
+    }
+
+    /// Whether `bounds` and `infinite_regions` know how to find finite regions in `dims`
+    /// dimensions.
+    fn supports_areas(&self, _dims: usize) -> bool {
+        true
+    }
+
+    /// A box that contains every finite region completely. By default this is the coordinates'
+    /// bounding box.
+    fn bounds(&self, coords: &[Loc]) -> Bounds {
+        Bounds::bounding_box(coords)
+    }
+
+    /// Labels every location in `bounds` with its closest coordinate. Fails if `bounds` has too
+    /// many locations.
+    fn ownership_grid(&self, coords: &[Loc]) -> Result<OwnershipGrid> {
+        voronoi::nearest_coord_grid(coords, self.bounds(coords), |a, b| self.dist(a, b))
+    }
+
+    /// Which coordinates' regions extend infinitely far, in the same order as `coords`.
+    ///
+    /// By default a region is infinite if it reaches the edge of `bounds`. Regions are
+    /// star-shaped around their coordinate, so an infinite one must cross that edge, and since
+    /// the bounds contain every finite region, anything that reaches the edge is infinite.
+    fn infinite_regions(&self, coords: &[Loc], grid: &OwnershipGrid) -> Vec<bool> {
+        let mut infinite = vec![false; coords.len()];
+        for loc in grid.locs().filter(|loc| grid.is_boundary(loc)) {
+            if let Owner::Coord(coord_idx) = grid.get(&loc) {
+                infinite[coord_idx] = true;
+            }
+        }
+        infinite
+    }
+
+    /// Which coordinates' regions extend infinitely far, if that can be told without building a
+    /// grid. By default it can't.
+    fn known_infinite_regions(&self, _coords: &[Loc]) -> Option<Vec<bool>> {
+        None
+    }
+
+    /// Number of locations whose total distance to every coordinate is below `threshold`.
+    ///
+    /// By default this checks every location in the bounding box grown by `threshold / n` on
+    /// each side: any further out and each coordinate alone contributes more than `threshold / n`.
+    fn safe_region_size(&self, coords: &[Loc], threshold: i64) -> usize {
+        if coords.is_empty() {
+            return 0;
+        }
+        let reach = threshold / coords.len() as i64 + 1;
+        Bounds::bounding_box(coords)
+            .grow(reach)
+            .locs()
+            .filter(|loc| {
+                coords
+                    .iter()
+                    .map(|coord| self.dist(coord, loc))
+                    .sum::<f64>()
+                    < threshold as f64
+            })
+            .count()
+    }
+}
+
+pub struct Manhattan;
+/// Manhattan distance with each axis scaled by a weight.
+pub struct WeightedManhattan {
+    pub weights: Vec<u32>,
+}
+pub struct Chebyshev;
+pub struct Euclidean;
+
+impl Metric for Manhattan {
+    fn name(&self) -> String {
+        "manhattan".to_string()
+    }
+
+    fn dist(&self, a: &[i64], b: &[i64]) -> f64 {
+        crate::manhattan_dist(a, b) as f64
+    }
+
+    fn ownership_grid(&self, coords: &[Loc]) -> Result<OwnershipGrid> {
+        voronoi::flood_fill_grid(coords)
+    }
+
+    fn safe_region_size(&self, coords: &[Loc], threshold: i64) -> usize {
+        safe_region::region_size(coords, threshold)
+    }
+}
+
+/// Stepping out of the bounding box adds the same distance to every coordinate, so the owner of
+/// an edge location owns everything beyond it and the bounding box defaults hold.
+impl Metric for WeightedManhattan {
+    fn name(&self) -> String {
+        let weights: Vec<String> = self.weights.iter().map(|w| w.to_string()).collect();
+        format!("weighted:{}", weights.join(","))
+    }
+
+    fn dist(&self, a: &[i64], b: &[i64]) -> f64 {
+        a.iter()
+            .zip(b)
+            .zip(&self.weights)
+            .map(|((a, b), &weight)| weight as i64 * (a - b).abs())
+            .sum::<i64>() as f64
+    }
+
+    fn check_dims(&self, dims: usize) -> Result<()> {
+        if self.weights.len() == dims {
+            Ok(())
+        } else {
+            Err(From::from(format!(
+                "{} weights given for {}-dimensional coordinates",
+                self.weights.len(),
+                dims
+            )))
+        }
+    }
+}
+
+impl Metric for Chebyshev {
+    fn name(&self) -> String {
+        "chebyshev".to_string()
+    }
+
+    fn dist(&self, a: &[i64], b: &[i64]) -> f64 {
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| (a - b).abs())
+            .max()
+            .unwrap_or(0) as f64
+    }
+
+    fn supports_areas(&self, dims: usize) -> bool {
+        dims <= 2
+    }
+
+    /// In 2D, Chebyshev distance is Manhattan distance on axes rotated by 45 degrees, so finite
+    /// regions stay inside the diamond bounding the coordinates along the diagonals `x + y` and
+    /// `x - y`, which can stick out of the ordinary bounding box. This is the box around that
+    /// diamond.
+    fn bounds(&self, coords: &[Loc]) -> Bounds {
+        if coords.first().map_or(0, |coord| coord.len()) < 2 {
+            return Bounds::bounding_box(coords);
+        }
+        let diagonals: Vec<Loc> = coords
+            .iter()
+            .map(|coord| vec![coord[0] + coord[1], coord[0] - coord[1]])
+            .collect();
+        let Bounds { min, max } = Bounds::bounding_box(&diagonals);
+        let (min_u, min_v, max_u, max_v) = (min[0], min[1], max[0], max[1]);
+        Bounds {
+            min: vec![
+                div_floor(min_u + min_v, 2) - 1,
+                div_floor(min_u - max_v, 2) - 1,
+            ],
+            max: vec![
+                div_floor(max_u + max_v, 2) + 1,
+                div_floor(max_u - min_v, 2) + 1,
+            ],
+        }
+    }
+}
+
+impl Metric for Euclidean {
+    fn name(&self) -> String {
+        "euclidean".to_string()
+    }
+
+    fn dist(&self, a: &[i64], b: &[i64]) -> f64 {
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| ((a - b) as f64).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+
+    fn supports_areas(&self, dims: usize) -> bool {
+        dims == 2
+    }
+
+    /// A finite Euclidean region is the polygon spanned by its Voronoi vertices, the centres of
+    /// empty circles through three coordinates. Those can lie far outside the bounding box when
+    /// three coordinates are nearly collinear, so the bounds are grown to take in every one that
+    /// has a coordinate inside the convex hull, and so a finite region, around it.
+    fn bounds(&self, coords: &[Loc]) -> Bounds {
+        let mut bounds = Bounds::bounding_box(coords);
+        let points = points_2d(coords);
+        let infinite = on_hull_boundary(&points);
+        let vertices = voronoi_vertices(&points)
+            .into_iter()
+            .filter(|(_vertex, triple)| triple.iter().any(|&idx| !infinite[idx]));
+        for ((x, y), _triple) in vertices {
+            bounds.min = vec![
+                bounds.min[0].min(x.floor() as i64),
+                bounds.min[1].min(y.floor() as i64),
+            ];
+            bounds.max = vec![
+                bounds.max[0].max(x.ceil() as i64),
+                bounds.max[1].max(y.ceil() as i64),
+            ];
+        }
+        bounds.grow(1)
+    }
+
+    /// A Euclidean region is infinite exactly when its coordinate lies on the boundary of the
+    /// coordinates' convex hull. An infinite region can be a wedge thin enough to slip between
+    /// the grid's locations, so this doesn't rely on the grid at all.
+    fn infinite_regions(&self, coords: &[Loc], _grid: &OwnershipGrid) -> Vec<bool> {
+        on_hull_boundary(&points_2d(coords))
+    }
+
+    fn known_infinite_regions(&self, coords: &[Loc]) -> Option<Vec<bool>> {
+        if coords.first().map_or(0, |coord| coord.len()) != 2 {
+            return None;
+        }
+        Some(on_hull_boundary(&points_2d(coords)))
+    }
+}
+
+/// Looks a metric up by name: `manhattan`, `chebyshev`, `euclidean` or `weighted:<w1>,<w2>,...`
+/// with one weight per axis.
+pub fn metric_by_name(name: &str) -> Result<Box<dyn Metric>> {
+    match name {
+        "manhattan" => Ok(Box::new(Manhattan)),
+        "chebyshev" => Ok(Box::new(Chebyshev)),
+        "euclidean" => Ok(Box::new(Euclidean)),
+        _ if name.starts_with("weighted:") => {
+            let weights = name["weighted:".len()..]
+                .split(',')
+                .map(|weight| weight.trim().parse())
+                .collect::<std::result::Result<Vec<u32>, _>>()?;
+            if weights.contains(&0) {
+                return Err(From::from("weights must be at least 1"));
+            }
+            Ok(Box::new(WeightedManhattan { weights }))
+        }
+        _ => Err(From::from(format!("unknown metric: {}", name))),
+    }
+}
+
+/// A location in 2D, for the geometry that only works there.
+type Point = (i64, i64);
+
+fn points_2d(coords: &[Loc]) -> Vec<Point> {
+    coords.iter().map(|coord| (coord[0], coord[1])).collect()
+}
+
+fn div_floor(a: i64, b: i64) -> i64 {
+    a.div_euclid(b)
+}
+
+fn cross(o: Point, a: Point, b: Point) -> i64 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+/// Andrew's monotone chain. Returns the hull's corners in counter-clockwise order, leaving out
+/// points in the middle of an edge.
+fn convex_hull(points: &[Point]) -> Vec<Point> {
+    let mut sorted = points.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+    let mut hull: Vec<Point> = Vec::with_capacity(2 * sorted.len());
+    for half in [sorted.clone(), sorted.iter().rev().cloned().collect()].iter() {
+        let start = hull.len();
+        for &p in half {
+            while hull.len() >= start + 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0
+            {
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        hull.pop();
+    }
+    hull
+}
+
+/// Whether each point lies on the boundary of the points' convex hull, rather than strictly
+/// inside it.
+fn on_hull_boundary(points: &[Point]) -> Vec<bool> {
+    let hull = convex_hull(points);
+    let is_on_boundary = |p: Point| {
+        hull.len() < 3
+            || (0..hull.len()).any(|i| {
+                let (a, b) = (hull[i], hull[(i + 1) % hull.len()]);
+                cross(a, b, p) == 0
+                    && a.0.min(b.0) <= p.0
+                    && p.0 <= a.0.max(b.0)
+                    && a.1.min(b.1) <= p.1
+                    && p.1 <= a.1.max(b.1)
+            })
+    };
+    points.iter().map(|&p| is_on_boundary(p)).collect()
+}
+
+/// Centres of circles through three coordinates with no coordinate strictly inside, along with
+/// the indices of those three coordinates.
+fn voronoi_vertices(coords: &[Point]) -> Vec<((f64, f64), [usize; 3])> {
+    let mut vertices = Vec::new();
+    let n = coords.len();
+    for i in 0..n {
+        for j in i + 1..n {
+            for k in j + 1..n {
+                let (a, b, c) = (coords[i], coords[j], coords[k]);
+                let d = 2 * cross(a, b, c);
+                if d == 0 {
+                    continue;
+                }
+                let norm = |(x, y): Point| (x * x + y * y) as f64;
+                let (na, nb, nc) = (norm(a), norm(b), norm(c));
+                let d = d as f64;
+                let x =
+                    (na * (b.1 - c.1) as f64 + nb * (c.1 - a.1) as f64 + nc * (a.1 - b.1) as f64)
+                        / d;
+                let y =
+                    (na * (c.0 - b.0) as f64 + nb * (a.0 - c.0) as f64 + nc * (b.0 - a.0) as f64)
+                        / d;
+                let dist_sq = |(px, py): Point| (px as f64 - x).powi(2) + (py as f64 - y).powi(2);
+                let radius_sq = dist_sq(a);
+                let is_empty = coords
+                    .iter()
+                    .all(|&p| dist_sq(p) >= radius_sq * (1.0 - 1e-9));
+                if is_empty {
+                    vertices.push(((x, y), [i, j, k]));
+                }
+            }
+        }
+    }
+    vertices
+}
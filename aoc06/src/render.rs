@@ -0,0 +1,164 @@
+use crate::voronoi::{Area, Owner, OwnershipGrid};
+use crate::{format_loc, Loc};
+use std::fmt::Write;
+
+/// Whether a location is in the region to highlight.
+pub type IsSafe<'a> = dyn Fn(&[i64]) -> bool + 'a;
+
+/// Draws a 2D ownership grid the way the puzzle does: each coordinate is an uppercase letter,
+/// the locations closest to it are the same letter in lowercase and ties are `.`. With more than
+/// 26 coordinates the letters repeat. Locations in `safe_region` are drawn as `#`, as in the
+/// puzzle's part 2, and a legend afterwards lists which regions are infinite.
+pub fn ascii(
+    coords: &[Loc],
+    grid: &OwnershipGrid,
+    areas: &[Area],
+    safe_region: Option<&IsSafe>,
+) -> String {
+    let mut map = String::new();
+    let (min, max) = (&grid.bounds.min, &grid.bounds.max);
+    for y in min[1]..=max[1] {
+        for x in min[0]..=max[0] {
+            let loc = [x, y];
+            let c = match grid.get(&loc) {
+                Owner::Coord(coord_idx) if coords[coord_idx] == loc => {
+                    letter(coord_idx).to_ascii_uppercase()
+                }
+                _ if safe_region.is_some_and(|is_safe| is_safe(&loc)) => '#',
+                Owner::Coord(coord_idx) => letter(coord_idx),
+                Owner::Tie | Owner::Unclaimed => '.',
+            };
+            map.push(c);
+        }
+        map.push('\n');
+    }
+    let infinite: Vec<String> = areas
+        .iter()
+        .enumerate()
+        .filter(|(_coord_idx, &area)| area == Area::Infinite)
+        .map(|(coord_idx, _area)| letter(coord_idx).to_string())
+        .collect();
+    writeln!(map, "infinite: {}", infinite.join(" ")).unwrap();
+    map
+}
+
+fn letter(coord_idx: usize) -> char {
+    (b'a' + (coord_idx % 26) as u8) as char
+}
+
+const CELL_SIZE: i64 = 4;
+
+/// Draws a 2D ownership grid as an SVG image, one square per location. Each region gets its own
+/// hue; infinite regions are drawn washed out, ties are light grey and the coordinates are black
+/// dots. Locations in `safe_region` are darkened.
+pub fn svg(
+    coords: &[Loc],
+    grid: &OwnershipGrid,
+    areas: &[Area],
+    safe_region: Option<&IsSafe>,
+) -> String {
+    let (min, max) = (&grid.bounds.min, &grid.bounds.max);
+    let width = (max[0] - min[0] + 1) * CELL_SIZE;
+    let height = (max[1] - min[1] + 1) * CELL_SIZE;
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}" shape-rendering="crispEdges">"#,
+        width, height, width, height
+    )
+    .unwrap();
+
+    for y in min[1]..=max[1] {
+        // merge runs of the same owner on a row into a single rectangle
+        let mut run_start = min[0];
+        for x in min[0]..=max[0] + 1 {
+            let run_ended = x > max[0] || grid.get(&[x, y]) != grid.get(&[run_start, y]);
+            if run_ended {
+                let fill = match grid.get(&[run_start, y]) {
+                    Owner::Coord(coord_idx) => {
+                        region_colour(coord_idx, areas[coord_idx] == Area::Infinite)
+                    }
+                    Owner::Tie | Owner::Unclaimed => "#dddddd".to_string(),
+                };
+                rect(
+                    &mut svg,
+                    run_start - min[0],
+                    y - min[1],
+                    x - run_start,
+                    &fill,
+                    None,
+                );
+                run_start = x;
+            }
+        }
+    }
+
+    if let Some(is_safe) = safe_region {
+        for y in min[1]..=max[1] {
+            let mut run_start = None;
+            for x in min[0]..=max[0] + 1 {
+                let safe = x <= max[0] && is_safe(&[x, y]);
+                match (safe, run_start) {
+                    (true, None) => run_start = Some(x),
+                    (false, Some(start)) => {
+                        rect(
+                            &mut svg,
+                            start - min[0],
+                            y - min[1],
+                            x - start,
+                            "#000000",
+                            Some(0.35),
+                        );
+                        run_start = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    for (coord_idx, coord) in coords.iter().enumerate() {
+        writeln!(
+            svg,
+            r#"<circle cx="{}" cy="{}" r="{}" fill="black"><title>{}: {}</title></circle>"#,
+            (coord[0] - min[0]) * CELL_SIZE + CELL_SIZE / 2,
+            (coord[1] - min[1]) * CELL_SIZE + CELL_SIZE / 2,
+            CELL_SIZE,
+            format_loc(coord),
+            match areas[coord_idx] {
+                Area::Finite(area) => area.to_string(),
+                Area::Infinite => "infinite".to_string(),
+            }
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn rect(svg: &mut String, x: i64, y: i64, len: i64, fill: &str, opacity: Option<f64>) {
+    write!(
+        svg,
+        r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}""#,
+        x * CELL_SIZE,
+        y * CELL_SIZE,
+        len * CELL_SIZE,
+        CELL_SIZE,
+        fill
+    )
+    .unwrap();
+    if let Some(opacity) = opacity {
+        write!(svg, r#" fill-opacity="{}""#, opacity).unwrap();
+    }
+    svg.push_str("/>\n");
+}
+
+/// Spreads hues around the colour wheel by the golden angle so neighbouring indices differ.
+fn region_colour(coord_idx: usize, infinite: bool) -> String {
+    let hue = (coord_idx as f64 * 137.508) % 360.0;
+    if infinite {
+        format!("hsl({:.0},25%,85%)", hue)
+    } else {
+        format!("hsl({:.0},70%,55%)", hue)
+    }
+}
@@ -0,0 +1,195 @@
+//! The region of locations whose total Manhattan distance to every coordinate is below a
+//! threshold.
+//!
+//! Total Manhattan distance separates into a sum over the axes, `F1(x1) + F2(x2) + ...`, where
+//! `Fi(v)` is the sum of `|v - ci|` over the coordinates' values along axis `i`. Each `Fi` is
+//! convex, so the values with `Fi` below the threshold form a single run, and the region is every
+//! combination of values from those runs whose sums add up to less than the threshold. Nothing
+//! limits the region to the coordinates' bounding box.
+
+use crate::Loc;
+
+/// The locations in the region that agree on every axis but the last, from `min` to `max`
+/// inclusive along the last axis.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Span {
+    pub prefix: Loc,
+    pub min: i64,
+    pub max: i64,
+}
+
+/// `Σ|v - vi|` for each whole-numbered `v` where it is below `threshold`.
+struct Profile {
+    first: i64,
+    sums: Vec<i64>,
+}
+
+impl Profile {
+    /// O(n log n) to sort, then O(1) per value of `v`.
+    fn new(mut values: Vec<i64>, threshold: i64) -> Profile {
+        values.sort_unstable();
+        let n = values.len() as i64;
+        let (min, max) = match (values.first(), values.last()) {
+            (Some(&min), Some(&max)) if threshold > 0 => (min, max),
+            _ => {
+                return Profile {
+                    first: 0,
+                    sums: Vec::new(),
+                }
+            }
+        };
+        // beyond this far outside the values, every term alone adds up past the threshold
+        let reach = threshold / n + 1;
+        let mut v = min - reach;
+        let mut sum: i64 = values.iter().map(|&value| value - v).sum();
+        let mut num_at_or_below = 0;
+        let mut first = None;
+        let mut sums = Vec::new();
+        while v <= max + reach {
+            if sum < threshold {
+                first.get_or_insert(v);
+                sums.push(sum);
+            } else if first.is_some() {
+                break;
+            }
+            while num_at_or_below < values.len() && values[num_at_or_below] <= v {
+                num_at_or_below += 1;
+            }
+            sum += num_at_or_below as i64 - (n - num_at_or_below as i64);
+            v += 1;
+        }
+        Profile {
+            first: first.unwrap_or(0),
+            sums,
+        }
+    }
+
+    /// The run of `v` with a sum below `limit`, if any.
+    fn run_below(&self, limit: i64) -> Option<(i64, i64)> {
+        let min_idx = (0..self.sums.len()).min_by_key(|&idx| self.sums[idx])?;
+        if self.sums[min_idx] >= limit {
+            return None;
+        }
+        // sums decrease up to min_idx and increase after it
+        let left = self.sums[..min_idx].partition_point(|&sum| sum >= limit);
+        let right = min_idx + self.sums[min_idx..].partition_point(|&sum| sum < limit) - 1;
+        Some((self.first + left as i64, self.first + right as i64))
+    }
+}
+
+fn profiles(coords: &[Loc], threshold: i64) -> Vec<Profile> {
+    let dims = coords.first().map_or(0, |coord| coord.len());
+    (0..dims)
+        .map(|axis| Profile::new(coords.iter().map(|coord| coord[axis]).collect(), threshold))
+        .collect()
+}
+
+/// Number of locations whose total Manhattan distance to the coordinates is below `threshold`.
+/// Runs in O(n log n) plus, in 2D, O(W log H) for a region W wide and H tall. In more dimensions
+/// the log factor applies to the region's extent along the last axis and the rest to its
+/// projection onto the other axes.
+pub fn region_size(coords: &[Loc], threshold: i64) -> usize {
+    let mut sorted_sums: Vec<Vec<i64>> = profiles(coords, threshold)
+        .into_iter()
+        .map(|profile| profile.sums)
+        .collect();
+    for sums in &mut sorted_sums {
+        sums.sort_unstable();
+    }
+    if sorted_sums.is_empty() {
+        return 0;
+    }
+    count_below(&sorted_sums, threshold)
+}
+
+/// Number of ways to pick one value per axis adding up to less than `limit`.
+fn count_below(sorted_sums: &[Vec<i64>], limit: i64) -> usize {
+    match sorted_sums {
+        [] => 1,
+        [last] => last.partition_point(|&sum| sum < limit),
+        [first, rest @ ..] => first
+            .iter()
+            .take_while(|&&sum| sum < limit)
+            .map(|&sum| count_below(rest, limit - sum))
+            .sum(),
+    }
+}
+
+/// The region as runs along the last axis, ordered by the other axes with the first axis
+/// changing slowest.
+pub fn outline(coords: &[Loc], threshold: i64) -> Vec<Span> {
+    let profiles = profiles(coords, threshold);
+    let mut spans = Vec::new();
+    if let Some((last, rest)) = profiles.split_last() {
+        collect_spans(rest, last, &mut Vec::new(), threshold, &mut spans);
+    }
+    spans
+}
+
+fn collect_spans(
+    profiles: &[Profile],
+    last: &Profile,
+    prefix: &mut Loc,
+    limit: i64,
+    spans: &mut Vec<Span>,
+) {
+    match profiles.split_first() {
+        None => {
+            if let Some((min, max)) = last.run_below(limit) {
+                spans.push(Span {
+                    prefix: prefix.clone(),
+                    min,
+                    max,
+                });
+            }
+        }
+        Some((profile, rest)) => {
+            for (idx, &sum) in profile.sums.iter().enumerate() {
+                prefix.push(profile.first + idx as i64);
+                collect_spans(rest, last, prefix, limit - sum, spans);
+                prefix.pop();
+            }
+        }
+    }
+}
+
+#[test]
+fn test_region_size() {
+    let coords = vec![
+        vec![1, 1],
+        vec![1, 6],
+        vec![8, 3],
+        vec![3, 4],
+        vec![5, 5],
+        vec![8, 9],
+    ];
+    assert_eq!(region_size(&coords, 32), 16);
+}
+
+#[test]
+fn test_region_past_bounding_box() {
+    // everything within distance 2 of the origin, which the bounding box is just the origin of
+    assert_eq!(region_size(&[vec![0, 0]], 3), 13);
+    assert_eq!(
+        outline(&[vec![0, 0]], 2),
+        vec![
+            Span {
+                prefix: vec![-1],
+                min: 0,
+                max: 0,
+            },
+            Span {
+                prefix: vec![0],
+                min: -1,
+                max: 1,
+            },
+            Span {
+                prefix: vec![1],
+                min: 0,
+                max: 0,
+            },
+        ]
+    );
+    assert_eq!(region_size(&[vec![-5, -5], vec![-3, -5]], 6), 11);
+    assert_eq!(region_size(&[vec![4, -3], vec![-6, -4]], 39), 672);
+}
@@ -0,0 +1,254 @@
+use crate::{format_loc, Loc, Result};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+
+/// Most locations an ownership grid may have, so a far-flung input gives an error rather than
+/// running out of memory.
+pub const MAX_GRID_LEN: usize = 1 << 26;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Owner {
+    /// Not yet reached by the flood fill.
+    Unclaimed,
+    /// Closest to the coordinate with this index.
+    Coord(usize),
+    /// Equally close to two or more coordinates.
+    Tie,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Area {
+    Finite(usize),
+    Infinite,
+}
+
+/// An inclusive box of locations, in as many dimensions as `min` and `max` have.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Bounds {
+    pub min: Loc,
+    pub max: Loc,
+}
+
+impl Bounds {
+    pub fn bounding_box(locs: &[Loc]) -> Bounds {
+        let dims = locs.first().map_or(0, |loc| loc.len());
+        let mut bounds = Bounds {
+            min: vec![i64::MAX; dims],
+            max: vec![i64::MIN; dims],
+        };
+        for loc in locs {
+            for (axis, &value) in loc.iter().enumerate().take(dims) {
+                bounds.min[axis] = bounds.min[axis].min(value);
+                bounds.max[axis] = bounds.max[axis].max(value);
+            }
+        }
+        bounds
+    }
+
+    /// The same box with `reach` more locations on every side.
+    pub fn grow(&self, reach: i64) -> Bounds {
+        Bounds {
+            min: self.min.iter().map(|min| min - reach).collect(),
+            max: self.max.iter().map(|max| max + reach).collect(),
+        }
+    }
+
+    pub fn dims(&self) -> usize {
+        self.min.len()
+    }
+
+    /// Number of locations along each axis.
+    pub fn extents(&self) -> Vec<usize> {
+        self.min
+            .iter()
+            .zip(&self.max)
+            .map(|(min, max)| (max - min + 1) as usize)
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.extents().iter().product()
+    }
+
+    pub fn contains(&self, loc: &[i64]) -> bool {
+        (0..self.dims()).all(|axis| self.min[axis] <= loc[axis] && loc[axis] <= self.max[axis])
+    }
+
+    pub fn is_boundary(&self, loc: &[i64]) -> bool {
+        (0..self.dims()).any(|axis| loc[axis] == self.min[axis] || loc[axis] == self.max[axis])
+    }
+
+    /// Every location in the box, with the first axis changing fastest.
+    pub fn locs(&self) -> impl Iterator<Item = Loc> {
+        let min = self.min.clone();
+        let extents = self.extents();
+        (0..self.len()).map(move |mut idx| {
+            min.iter()
+                .zip(&extents)
+                .map(|(min, &extent)| {
+                    let offset = idx % extent;
+                    idx /= extent;
+                    min + offset as i64
+                })
+                .collect()
+        })
+    }
+
+    /// Position of `loc` in the order `locs` yields them.
+    fn idx(&self, loc: &[i64]) -> usize {
+        let mut idx = 0;
+        let mut stride = 1;
+        for (axis, extent) in self.extents().into_iter().enumerate() {
+            idx += (loc[axis] - self.min[axis]) as usize * stride;
+            stride *= extent;
+        }
+        idx
+    }
+}
+
+/// The closest coordinate to every location in some box.
+#[derive(Debug)]
+pub struct OwnershipGrid {
+    pub bounds: Bounds,
+    cells: Vec<Owner>,
+}
+
+impl OwnershipGrid {
+    fn new(bounds: Bounds) -> Result<OwnershipGrid> {
+        let len = bounds
+            .min
+            .iter()
+            .zip(&bounds.max)
+            .try_fold(1usize, |len, (min, max)| {
+                let extent = usize::try_from(max.checked_sub(*min)?.checked_add(1)?).ok()?;
+                len.checked_mul(extent)
+            })
+            .filter(|&len| len <= MAX_GRID_LEN)
+            .ok_or_else(|| {
+                format!(
+                    "too many locations between ({}) and ({}) to make a grid",
+                    format_loc(&bounds.min),
+                    format_loc(&bounds.max)
+                )
+            })?;
+        Ok(OwnershipGrid {
+            cells: vec![Owner::Unclaimed; len],
+            bounds,
+        })
+    }
+
+    pub fn get(&self, loc: &[i64]) -> Owner {
+        self.cells[self.bounds.idx(loc)]
+    }
+
+    pub fn is_boundary(&self, loc: &[i64]) -> bool {
+        self.bounds.is_boundary(loc)
+    }
+
+    pub fn locs(&self) -> impl Iterator<Item = Loc> {
+        self.bounds.locs()
+    }
+
+    fn neighbours(&self, loc: &[i64]) -> Vec<Loc> {
+        let mut neighbours = Vec::with_capacity(2 * loc.len());
+        for axis in 0..loc.len() {
+            for step in &[-1, 1] {
+                let mut neighbour = loc.to_vec();
+                neighbour[axis] += step;
+                if self.bounds.contains(&neighbour) {
+                    neighbours.push(neighbour);
+                }
+            }
+        }
+        neighbours
+    }
+}
+
+/// Labels every location in the bounding box with its closest coordinate by Manhattan distance,
+/// flooding outwards from all coordinates at once, one ring of distance at a time. A location
+/// reached in the same ring from two different coordinates, or from a tied location, is itself
+/// a tie.
+///
+/// Manhattan shortest paths between two locations never leave their bounding box, so flooding
+/// inside the box gives exact distances. Runs in time linear in the size of the box.
+pub fn flood_fill_grid(coords: &[Loc]) -> Result<OwnershipGrid> {
+    let mut grid = OwnershipGrid::new(Bounds::bounding_box(coords))?;
+    let mut dist = vec![usize::MAX; grid.cells.len()];
+    let mut queue = VecDeque::new();
+    for (coord_idx, coord) in coords.iter().enumerate() {
+        let idx = grid.bounds.idx(coord);
+        grid.cells[idx] = match grid.cells[idx] {
+            Owner::Unclaimed => {
+                dist[idx] = 0;
+                queue.push_back(coord.clone());
+                Owner::Coord(coord_idx)
+            }
+            // two coordinates in the same place
+            _ => Owner::Tie,
+        };
+    }
+
+    while let Some(loc) = queue.pop_front() {
+        let loc_idx = grid.bounds.idx(&loc);
+        let owner = grid.cells[loc_idx];
+        let neighbour_dist = dist[loc_idx] + 1;
+        for neighbour in grid.neighbours(&loc) {
+            let idx = grid.bounds.idx(&neighbour);
+            if grid.cells[idx] == Owner::Unclaimed {
+                grid.cells[idx] = owner;
+                dist[idx] = neighbour_dist;
+                queue.push_back(neighbour);
+            } else if dist[idx] == neighbour_dist && grid.cells[idx] != owner {
+                grid.cells[idx] = Owner::Tie;
+            }
+        }
+    }
+    Ok(grid)
+}
+
+/// Labels every location in `bounds` by comparing its distance to every coordinate. Works for
+/// any distance function, in time proportional to the size of the box times `n`.
+pub fn nearest_coord_grid<F>(coords: &[Loc], bounds: Bounds, dist: F) -> Result<OwnershipGrid>
+where
+    F: Fn(&[i64], &[i64]) -> f64,
+{
+    let mut grid = OwnershipGrid::new(bounds)?;
+    for (idx, loc) in grid.bounds.locs().enumerate() {
+        let mut owner = Owner::Unclaimed;
+        let mut closest_dist = f64::INFINITY;
+        for (coord_idx, coord) in coords.iter().enumerate() {
+            let dist = dist(coord, &loc);
+            if dist == closest_dist {
+                owner = Owner::Tie;
+            } else if dist < closest_dist {
+                closest_dist = dist;
+                owner = Owner::Coord(coord_idx);
+            }
+        }
+        grid.cells[idx] = owner;
+    }
+    Ok(grid)
+}
+
+/// The area of every coordinate's region, in the same order as `coords`. The grid must cover
+/// every finite region completely.
+pub fn areas(grid: &OwnershipGrid, infinite_regions: &[bool]) -> Vec<Area> {
+    let mut areas: Vec<Area> = infinite_regions
+        .iter()
+        .map(|&infinite| {
+            if infinite {
+                Area::Infinite
+            } else {
+                Area::Finite(0)
+            }
+        })
+        .collect();
+    for owner in &grid.cells {
+        if let Owner::Coord(coord_idx) = *owner {
+            if let Area::Finite(area) = areas[coord_idx] {
+                areas[coord_idx] = Area::Finite(area + 1);
+            }
+        }
+    }
+    areas
+}
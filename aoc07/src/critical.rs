@@ -0,0 +1,152 @@
+use crate::cycle::CycleError;
+use crate::{make_task_to_num_dependencies_map, topo_sort, Task};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Timing {
+    /// The soonest the task can start with unlimited workers.
+    pub earliest_start: usize,
+    /// The latest the task can start without delaying the whole plan.
+    pub latest_start: usize,
+    pub duration: usize,
+}
+
+impl Timing {
+    /// How long the task can be delayed without delaying the whole plan.
+    pub fn slack(&self) -> usize {
+        self.latest_start - self.earliest_start
+    }
+}
+
+#[derive(Debug)]
+pub struct Analysis {
+    pub timings: HashMap<Task, Timing>,
+    /// A longest chain of tasks, each a dependency of the next. Where there's a choice, it goes
+    /// through the alphabetically smaller task.
+    pub critical_path: Vec<Task>,
+    /// The length of the critical path: no number of workers can finish sooner than this.
+    pub min_finish_time: usize,
+    /// The duration of every task added up.
+    pub total_work: usize,
+}
+
+impl Analysis {
+    /// No schedule with `num_workers` workers finishes sooner than this: not before the critical
+    /// path is done, and not before the workers have had time for all of the work between them.
+    pub fn lower_bound(&self, num_workers: usize) -> usize {
+        self.min_finish_time
+            .max(self.total_work.div_ceil(num_workers.max(1)))
+    }
+}
+
+/// Works out how early and how late every task can start, by going forwards through a
+/// topological order to find the earliest starts and then backwards to find the latest.
+pub fn analyze(
+    task_to_dependents: &HashMap<Task, Vec<Task>>,
+    duration: &dyn Fn(&str) -> usize,
+) -> Result<Analysis, CycleError> {
+    let ordering = topo_sort(
+        task_to_dependents,
+        make_task_to_num_dependencies_map(task_to_dependents),
+    )?;
+
+    let mut earliest_starts: HashMap<Task, usize> =
+        ordering.iter().map(|task| (task.clone(), 0)).collect();
+    for task in &ordering {
+        let earliest_finish = earliest_starts[task] + duration(task);
+        for dependent in &task_to_dependents[task] {
+            let earliest_start = earliest_starts.get_mut(dependent).unwrap();
+            *earliest_start = (*earliest_start).max(earliest_finish);
+        }
+    }
+    let min_finish_time = ordering
+        .iter()
+        .map(|task| earliest_starts[task] + duration(task))
+        .max()
+        .unwrap_or(0);
+
+    let mut timings: HashMap<Task, Timing> = HashMap::with_capacity(ordering.len());
+    for task in ordering.iter().rev() {
+        let latest_finish = task_to_dependents[task]
+            .iter()
+            .map(|dependent| timings[dependent].latest_start)
+            .min()
+            .unwrap_or(min_finish_time);
+        timings.insert(
+            task.clone(),
+            Timing {
+                earliest_start: earliest_starts[task],
+                latest_start: latest_finish - duration(task),
+                duration: duration(task),
+            },
+        );
+    }
+
+    // follow tasks with no slack that start the moment the previous one finishes
+    let mut critical_path = Vec::new();
+    let mut next = ordering
+        .iter()
+        .filter(|task| timings[*task].earliest_start == 0 && timings[*task].slack() == 0)
+        .min();
+    while let Some(task) = next {
+        critical_path.push(task.clone());
+        let finish = timings[task].earliest_start + timings[task].duration;
+        next = task_to_dependents[task]
+            .iter()
+            .filter(|dependent| {
+                timings[*dependent].earliest_start == finish && timings[*dependent].slack() == 0
+            })
+            .min();
+    }
+
+    Ok(Analysis {
+        total_work: ordering.iter().map(|task| duration(task)).sum(),
+        timings,
+        critical_path,
+        min_finish_time,
+    })
+}
+
+/// A table of every task's timings in alphabetical order, followed by the critical path.
+pub fn format_analysis(analysis: &Analysis) -> String {
+    let mut tasks: Vec<&Task> = analysis.timings.keys().collect();
+    tasks.sort_unstable();
+    let width = tasks
+        .iter()
+        .map(|task| task.len())
+        .max()
+        .unwrap_or(0)
+        .max(4);
+    let mut table = format!(
+        "{:>width$}  {:>8}  {:>8}  {:>8}  {:>8}\n",
+        "Task",
+        "Duration",
+        "Earliest",
+        "Latest",
+        "Slack",
+        width = width
+    );
+    for task in tasks {
+        let timing = analysis.timings[task];
+        writeln!(
+            table,
+            "{:>width$}  {:>8}  {:>8}  {:>8}  {:>8}",
+            task,
+            timing.duration,
+            timing.earliest_start,
+            timing.latest_start,
+            timing.slack(),
+            width = width
+        )
+        .unwrap();
+    }
+    writeln!(
+        table,
+        "Critical path: {} ({} seconds)",
+        analysis.critical_path.join(" → "),
+        analysis.min_finish_time
+    )
+    .unwrap();
+    table
+}
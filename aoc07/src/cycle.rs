@@ -0,0 +1,84 @@
+use crate::Task;
+use std::collections::HashMap;
+use std::fmt;
+
+/// The constraints can't all be satisfied: some tasks depend on each other in a loop.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CycleError {
+    /// One loop of tasks, each a dependency of the next, starting and ending with the same task.
+    pub cycle: Vec<Task>,
+    /// Every task that could never start, in alphabetical order.
+    pub blocked: Vec<Task>,
+}
+
+impl fmt::Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "dependency cycle {}; tasks that can never start: {}",
+            self.cycle.join(" → "),
+            self.blocked.join(" ")
+        )
+    }
+}
+
+impl std::error::Error for CycleError {}
+
+/// Checks that every task was started, given the number of unfinished dependencies each task had
+/// left once no more tasks could run. If any weren't, finds a cycle among them.
+pub fn check(
+    task_to_dependents: &HashMap<Task, Vec<Task>>,
+    task_to_num_dependencies: &HashMap<Task, usize>,
+) -> Result<(), CycleError> {
+    let mut blocked: Vec<Task> = task_to_num_dependencies
+        .iter()
+        .filter(|(_task, &num_dependencies)| num_dependencies > 0)
+        .map(|(task, _num_dependencies)| task.clone())
+        .collect();
+    if blocked.is_empty() {
+        return Ok(());
+    }
+    blocked.sort_unstable();
+
+    // every blocked task is waiting on a blocked dependency, so following dependencies
+    // backwards from any of them must eventually come back round to a task already seen
+    let mut task_to_blocked_dependencies: HashMap<Task, Vec<Task>> = HashMap::new();
+    for (dependency, dependents) in task_to_dependents {
+        if task_to_num_dependencies[dependency] == 0 {
+            continue;
+        }
+        for dependent in dependents {
+            task_to_blocked_dependencies
+                .entry(dependent.clone())
+                .or_default()
+                .push(dependency.clone());
+        }
+    }
+    let mut path = vec![blocked[0].clone()];
+    let mut path_positions = HashMap::new();
+    path_positions.insert(blocked[0].clone(), 0);
+    let cycle_start = loop {
+        let task = &path[path.len() - 1];
+        let dependency = task_to_blocked_dependencies[task].iter().min().unwrap();
+        if let Some(&position) = path_positions.get(dependency) {
+            break position;
+        }
+        path_positions.insert(dependency.clone(), path.len());
+        path.push(dependency.clone());
+    };
+
+    let mut cycle: Vec<Task> = path[cycle_start..].iter().rev().cloned().collect();
+    let first = (0..cycle.len()).min_by_key(|&idx| &cycle[idx]).unwrap();
+    cycle.rotate_left(first);
+    cycle.push(cycle[0].clone());
+    Err(CycleError { cycle, blocked })
+}
+
+#[test]
+fn test_example_with_cycle() {
+    let task_to_dependents =
+        crate::example_dependency_map(&["Step E must be finished before step C can begin."]);
+    let err = crate::part1(&task_to_dependents).unwrap_err();
+    assert_eq!(err.cycle, ["A", "B", "E", "C", "A"]);
+    assert_eq!(err.blocked, ["A", "B", "C", "D", "E", "F"]);
+}
@@ -0,0 +1,135 @@
+use crate::Task;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// What to draw on top of the dependency graph.
+#[derive(Debug, Default)]
+pub struct DotOptions {
+    /// Numbers the tasks in topological order and links them with dashed blue edges.
+    pub highlight_order: bool,
+    /// Draws these tasks, and the edges between them, in bold red.
+    pub critical_path: Option<Vec<Task>>,
+    /// Groups tasks into clusters by the length of the longest chain of dependencies before
+    /// them.
+    pub cluster_by_depth: bool,
+}
+
+/// A Graphviz digraph with a node per task, labelled with its duration, and an edge from every
+/// dependency to its dependent. Nodes keep their duration in a `duration` attribute, so the
+/// graph can be read back in with `--format dot`, which skips the `constraint=false` edges that
+/// highlight the order. Tasks and edges are written in alphabetical order so the output is
+/// stable. `ordering` must be a topological order of the tasks.
+pub fn to_dot(
+    task_to_dependents: &HashMap<Task, Vec<Task>>,
+    ordering: &[Task],
+    duration: &dyn Fn(&str) -> usize,
+    options: &DotOptions,
+) -> String {
+    let mut tasks: Vec<&Task> = task_to_dependents.keys().collect();
+    tasks.sort_unstable();
+    let positions: HashMap<&Task, usize> = if options.highlight_order {
+        ordering
+            .iter()
+            .enumerate()
+            .map(|(position, task)| (task, position))
+            .collect()
+    } else {
+        HashMap::new()
+    };
+    let critical_path = options.critical_path.as_deref().unwrap_or(&[]);
+    let is_critical_edge = |dependency: &Task, dependent: &Task| {
+        critical_path
+            .windows(2)
+            .any(|pair| &pair[0] == dependency && &pair[1] == dependent)
+    };
+
+    let mut dot = "digraph tasks {\n    rankdir=LR;\n    node [shape=box];\n".to_string();
+    let node = |task: &Task| {
+        // `\n` is a line break inside a Graphviz label, so the label is escaped piece by piece
+        let mut label = format!("{}\\n{}s", escape(task), duration(task));
+        if let Some(position) = positions.get(task) {
+            write!(label, "\\n#{}", position + 1).unwrap();
+        }
+        let mut node = format!(
+            "{} [label=\"{}\", duration={}",
+            quote(task),
+            label,
+            duration(task)
+        );
+        if critical_path.contains(task) {
+            node.push_str(", color=red, penwidth=2");
+        }
+        node.push_str("];");
+        node
+    };
+
+    if options.cluster_by_depth {
+        let depths = depths(task_to_dependents, ordering);
+        let max_depth = depths.values().cloned().max().unwrap_or(0);
+        for depth in 0..=max_depth {
+            writeln!(dot, "    subgraph cluster_depth_{} {{", depth).unwrap();
+            writeln!(dot, "        label=\"depth {}\";", depth).unwrap();
+            for task in tasks
+                .iter()
+                .filter(|task| depths.get(**task) == Some(&depth))
+            {
+                writeln!(dot, "        {}", node(task)).unwrap();
+            }
+            dot.push_str("    }\n");
+        }
+    } else {
+        for task in &tasks {
+            writeln!(dot, "    {}", node(task)).unwrap();
+        }
+    }
+
+    for dependency in &tasks {
+        let mut dependents: Vec<&Task> = task_to_dependents[*dependency].iter().collect();
+        dependents.sort_unstable();
+        for dependent in dependents {
+            write!(dot, "    {} -> {}", quote(dependency), quote(dependent)).unwrap();
+            if is_critical_edge(dependency, dependent) {
+                dot.push_str(" [color=red, penwidth=2]");
+            }
+            dot.push_str(";\n");
+        }
+    }
+    if options.highlight_order {
+        for pair in ordering.windows(2) {
+            writeln!(
+                dot,
+                "    {} -> {} [style=dashed, color=blue, constraint=false];",
+                quote(&pair[0]),
+                quote(&pair[1])
+            )
+            .unwrap();
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// The length of the longest chain of dependencies before each task.
+fn depths<'a>(
+    task_to_dependents: &HashMap<Task, Vec<Task>>,
+    ordering: &'a [Task],
+) -> HashMap<&'a Task, usize> {
+    let mut depths: HashMap<&Task, usize> = ordering.iter().map(|task| (task, 0)).collect();
+    for task in ordering {
+        let depth = depths[task] + 1;
+        for dependent in &task_to_dependents[task] {
+            if let Some(dependent_depth) = depths.get_mut(dependent) {
+                *dependent_depth = (*dependent_depth).max(depth);
+            }
+        }
+    }
+    depths
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", escape(s))
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
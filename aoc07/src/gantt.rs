@@ -0,0 +1,132 @@
+use crate::scheduler::{Assignment, Schedule};
+use crate::{format_order, Task};
+use std::fmt::Write;
+
+/// The puzzle's second-by-second table: what each worker is doing at the start of every second,
+/// and which tasks are done by then.
+pub fn table(schedule: &Schedule) -> String {
+    let mut table = "Second".to_string();
+    for worker in 0..schedule.num_workers {
+        write!(table, "   Worker {}", worker + 1).unwrap();
+    }
+    table.push_str("   Done\n");
+
+    let mut by_end: Vec<&Assignment> = schedule.assignments.iter().collect();
+    by_end.sort_by_key(|assignment| (assignment.end, &assignment.task));
+    let mut num_done = 0;
+    for second in 0..=schedule.finish_time {
+        while num_done < by_end.len() && by_end[num_done].end <= second {
+            num_done += 1;
+        }
+        write!(table, "{:>4}  ", second).unwrap();
+        for worker in 0..schedule.num_workers {
+            let task = task_at(schedule, worker, second).map_or(".", String::as_str);
+            write!(table, "   {:^8}", task).unwrap();
+        }
+        let done: Vec<Task> = by_end[..num_done].iter().map(|a| a.task.clone()).collect();
+        writeln!(table, "   {}", format_order(&done)).unwrap();
+    }
+    table
+}
+
+fn task_at(schedule: &Schedule, worker: usize, second: usize) -> Option<&Task> {
+    schedule
+        .assignments
+        .iter()
+        .find(|a| a.worker == worker && a.start <= second && second < a.end)
+        .map(|a| &a.task)
+}
+
+/// One line per worker, at most `width` columns wide. Each column covers the same number of
+/// seconds and shows the first letter of the task the worker was doing at the start of them, or
+/// `.` if it was idle.
+pub fn text(schedule: &Schedule, width: usize) -> String {
+    let seconds_per_column = schedule.finish_time.div_ceil(width.max(1)).max(1);
+    let num_columns = schedule.finish_time.div_ceil(seconds_per_column);
+    let mut chart = format!("{} seconds per column\n", seconds_per_column);
+    for worker in 0..schedule.num_workers {
+        write!(chart, "Worker {:<3}|", worker + 1).unwrap();
+        for column in 0..num_columns {
+            let task = task_at(schedule, worker, column * seconds_per_column);
+            chart.push(task.and_then(|task| task.chars().next()).unwrap_or('.'));
+        }
+        chart.push_str("|\n");
+    }
+    writeln!(chart, "Finished at {}", schedule.finish_time).unwrap();
+    chart
+}
+
+const ROW_HEIGHT: usize = 24;
+const LABEL_WIDTH: usize = 80;
+const TIMELINE_WIDTH: f64 = 1000.0;
+
+/// A timeline with a row per worker and a labelled bar per task.
+pub fn svg(schedule: &Schedule) -> String {
+    let scale = TIMELINE_WIDTH / schedule.finish_time.max(1) as f64;
+    let width = LABEL_WIDTH + TIMELINE_WIDTH as usize;
+    let height = (schedule.num_workers + 1) * ROW_HEIGHT;
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}" font-family="monospace" font-size="12">"#,
+        width, height, width, height
+    )
+    .unwrap();
+
+    for worker in 0..schedule.num_workers {
+        writeln!(
+            svg,
+            r#"<text x="4" y="{}">Worker {}</text>"#,
+            worker * ROW_HEIGHT + ROW_HEIGHT * 2 / 3,
+            worker + 1
+        )
+        .unwrap();
+    }
+    for assignment in &schedule.assignments {
+        let x = LABEL_WIDTH as f64 + assignment.start as f64 * scale;
+        let bar_width = (assignment.end - assignment.start) as f64 * scale;
+        let y = assignment.worker * ROW_HEIGHT + 2;
+        writeln!(
+            svg,
+            r#"<rect x="{:.1}" y="{}" width="{:.1}" height="{}" fill="hsl({},60%,70%)" stroke="black" stroke-width="0.5"><title>{}: {}..{}</title></rect>"#,
+            x,
+            y,
+            bar_width,
+            ROW_HEIGHT - 4,
+            task_hue(&assignment.task),
+            assignment.task,
+            assignment.start,
+            assignment.end
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            r#"<text x="{:.1}" y="{}" text-anchor="middle">{}</text>"#,
+            x + bar_width / 2.0,
+            y + ROW_HEIGHT * 2 / 3 - 2,
+            assignment.task
+        )
+        .unwrap();
+    }
+    // the time axis along the bottom
+    let axis_y = schedule.num_workers * ROW_HEIGHT + ROW_HEIGHT * 2 / 3;
+    for tick in 0..=10 {
+        let time = schedule.finish_time * tick / 10;
+        writeln!(
+            svg,
+            r#"<text x="{:.1}" y="{}" text-anchor="middle">{}</text>"#,
+            LABEL_WIDTH as f64 + time as f64 * scale,
+            axis_y,
+            time
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Spreads the hues of tasks with similar names around the colour wheel.
+fn task_hue(task: &str) -> u32 {
+    task.bytes()
+        .fold(0, |hue, byte| (hue * 31 + byte as u32 * 47) % 360)
+}
@@ -0,0 +1,270 @@
+use crate::cycle::CycleError;
+use crate::{Constraint, Task};
+use std::collections::{HashMap, HashSet};
+
+/// A dependency graph that can be edited in place. It keeps each task's number of dependencies
+/// and a topological order up to date as constraints come and go, and refuses any edit that would
+/// create a cycle.
+///
+/// The order is kept with the Pearce-Kelly algorithm: adding a constraint only reorders the tasks
+/// between its two ends that it actually affects. It's a valid order, but not necessarily the
+/// alphabetical one `topo_sort` gives.
+#[derive(Debug, Default)]
+pub struct TaskGraph {
+    task_to_dependents: HashMap<Task, Vec<Task>>,
+    task_to_dependencies: HashMap<Task, Vec<Task>>,
+    order: Vec<Task>,
+    positions: HashMap<Task, usize>,
+}
+
+impl TaskGraph {
+    pub fn new() -> Self {
+        TaskGraph::default()
+    }
+
+    pub fn from_constraints<I>(constraints: I) -> Result<Self, CycleError>
+    where
+        I: IntoIterator<Item = Constraint>,
+    {
+        let mut graph = TaskGraph::new();
+        for constraint in constraints {
+            graph.add_constraint(&constraint.dependency, &constraint.dependent)?;
+        }
+        Ok(graph)
+    }
+
+    pub fn num_dependencies(&self, task: &str) -> Option<usize> {
+        self.task_to_dependencies.get(task).map(Vec::len)
+    }
+
+    /// A topological order of every task.
+    pub fn order(&self) -> &[Task] {
+        &self.order
+    }
+
+    /// Adds a task with no constraints, after every other task in the order. Returns whether it
+    /// was new.
+    pub fn add_task(&mut self, task: &str) -> bool {
+        if self.positions.contains_key(task) {
+            return false;
+        }
+        self.task_to_dependents.insert(task.to_string(), Vec::new());
+        self.task_to_dependencies
+            .insert(task.to_string(), Vec::new());
+        self.positions.insert(task.to_string(), self.order.len());
+        self.order.push(task.to_string());
+        true
+    }
+
+    /// Removes a task along with every constraint it's part of. Returns whether it was there.
+    pub fn remove_task(&mut self, task: &str) -> bool {
+        let position = match self.positions.remove(task) {
+            Some(position) => position,
+            None => return false,
+        };
+        for dependent in self.task_to_dependents.remove(task).unwrap() {
+            self.task_to_dependencies
+                .get_mut(&dependent)
+                .unwrap()
+                .retain(|dependency| dependency != task);
+        }
+        for dependency in self.task_to_dependencies.remove(task).unwrap() {
+            self.task_to_dependents
+                .get_mut(&dependency)
+                .unwrap()
+                .retain(|dependent| dependent != task);
+        }
+        self.order.remove(position);
+        for (position, task) in self.order.iter().enumerate().skip(position) {
+            *self.positions.get_mut(task).unwrap() = position;
+        }
+        true
+    }
+
+    /// Requires `dependency` to finish before `dependent` begins, adding either task if it's new.
+    /// Fails, leaving the graph as it was apart from any new tasks, if `dependent` is already
+    /// needed for `dependency`.
+    pub fn add_constraint(&mut self, dependency: &str, dependent: &str) -> Result<(), CycleError> {
+        self.add_task(dependency);
+        self.add_task(dependent);
+        if self.task_to_dependents[dependency]
+            .iter()
+            .any(|known| known == dependent)
+        {
+            return Ok(());
+        }
+
+        let lower = self.positions[dependent];
+        let upper = self.positions[dependency];
+        if lower <= upper {
+            // everything that depends on `dependent`, up to where `dependency` is in the order
+            let mut path = HashMap::new();
+            let forward = self.reach(dependent, upper, &mut path, true);
+            if forward.iter().any(|task| task == dependency) {
+                return Err(self.cycle_through(dependency, dependent, &path));
+            }
+            // everything `dependency` depends on, back to where `dependent` is in the order
+            let backward = self.reach(dependency, lower, &mut HashMap::new(), false);
+            self.reorder(backward, forward);
+        }
+
+        self.task_to_dependents
+            .get_mut(dependency)
+            .unwrap()
+            .push(dependent.to_string());
+        self.task_to_dependencies
+            .get_mut(dependent)
+            .unwrap()
+            .push(dependency.to_string());
+        Ok(())
+    }
+
+    /// Returns whether the constraint was there. Removing a constraint never invalidates the
+    /// order.
+    pub fn remove_constraint(&mut self, dependency: &str, dependent: &str) -> bool {
+        let dependents = match self.task_to_dependents.get_mut(dependency) {
+            Some(dependents) => dependents,
+            None => return false,
+        };
+        let len = dependents.len();
+        dependents.retain(|known| known != dependent);
+        if dependents.len() == len {
+            return false;
+        }
+        self.task_to_dependencies
+            .get_mut(dependent)
+            .unwrap()
+            .retain(|known| known != dependency);
+        true
+    }
+
+    /// Every task reachable from `start` by following dependents (or dependencies, if not
+    /// `forward`) without going past `limit` in the order. Records how each task was reached in
+    /// `path`.
+    fn reach(
+        &self,
+        start: &str,
+        limit: usize,
+        path: &mut HashMap<Task, Task>,
+        forward: bool,
+    ) -> Vec<Task> {
+        let edges = if forward {
+            &self.task_to_dependents
+        } else {
+            &self.task_to_dependencies
+        };
+        let mut seen = HashSet::new();
+        seen.insert(start.to_string());
+        let mut stack = vec![start.to_string()];
+        let mut reached = Vec::new();
+        while let Some(task) = stack.pop() {
+            for next in &edges[&task] {
+                let position = self.positions[next];
+                let in_range = if forward {
+                    position <= limit
+                } else {
+                    position >= limit
+                };
+                if in_range && seen.insert(next.clone()) {
+                    path.insert(next.clone(), task.clone());
+                    stack.push(next.clone());
+                }
+            }
+            reached.push(task);
+        }
+        reached
+    }
+
+    /// Moves the tasks in `backward` ahead of the tasks in `forward`, reusing the positions they
+    /// take up between them and keeping each group's own order.
+    fn reorder(&mut self, mut backward: Vec<Task>, mut forward: Vec<Task>) {
+        backward.sort_unstable_by_key(|task| self.positions[task]);
+        forward.sort_unstable_by_key(|task| self.positions[task]);
+        let mut positions: Vec<usize> = backward
+            .iter()
+            .chain(&forward)
+            .map(|task| self.positions[task])
+            .collect();
+        positions.sort_unstable();
+        for (task, position) in backward.into_iter().chain(forward).zip(positions) {
+            self.positions.insert(task.clone(), position);
+            self.order[position] = task;
+        }
+    }
+
+    /// The cycle the constraint `dependency -> dependent` would close, given how the search from
+    /// `dependent` reached `dependency`, and every task it would block.
+    fn cycle_through(
+        &self,
+        dependency: &str,
+        dependent: &str,
+        path: &HashMap<Task, Task>,
+    ) -> CycleError {
+        let mut cycle = vec![dependency.to_string()];
+        while cycle[cycle.len() - 1] != dependent {
+            let previous = path[&cycle[cycle.len() - 1]].clone();
+            cycle.push(previous);
+        }
+        cycle.reverse();
+        cycle.insert(0, dependency.to_string());
+        // start from the alphabetically first task, as `cycle::check` does
+        cycle.pop();
+        let first = (0..cycle.len()).min_by_key(|&idx| &cycle[idx]).unwrap();
+        cycle.rotate_left(first);
+        cycle.push(cycle[0].clone());
+
+        let mut blocked = self.reach(dependent, self.order.len(), &mut HashMap::new(), true);
+        blocked.sort_unstable();
+        CycleError { cycle, blocked }
+    }
+}
+
+#[test]
+fn test_add_constraint_reorders() {
+    let mut graph = TaskGraph::new();
+    graph.add_constraint("c", "d").unwrap();
+    graph.add_constraint("a", "b").unwrap();
+    assert_eq!(graph.order(), ["c", "d", "a", "b"]);
+    graph.add_constraint("b", "c").unwrap();
+    assert_eq!(graph.order(), ["a", "b", "c", "d"]);
+    assert_eq!(graph.num_dependencies("c"), Some(1));
+}
+
+#[test]
+fn test_add_constraint_rejects_cycle() {
+    let mut graph = TaskGraph::new();
+    graph.add_constraint("a", "b").unwrap();
+    graph.add_constraint("b", "c").unwrap();
+    graph.add_constraint("c", "d").unwrap();
+    let err = graph.add_constraint("d", "b").unwrap_err();
+    assert_eq!(err.cycle, ["b", "c", "d", "b"]);
+    assert_eq!(err.blocked, ["b", "c", "d"]);
+    assert_eq!(graph.order(), ["a", "b", "c", "d"]);
+    assert_eq!(graph.num_dependencies("b"), Some(1));
+    assert!(!graph.remove_constraint("d", "b"));
+}
+
+#[test]
+fn test_remove_task_updates_positions() {
+    let mut graph = TaskGraph::new();
+    graph.add_constraint("a", "b").unwrap();
+    graph.add_constraint("b", "c").unwrap();
+    graph.add_constraint("c", "d").unwrap();
+    assert!(graph.remove_task("b"));
+    assert!(!graph.remove_task("b"));
+    assert_eq!(graph.order(), ["a", "c", "d"]);
+    assert_eq!(graph.num_dependencies("c"), Some(0));
+    // only possible without a cycle now that b is gone, and needs c and d moved ahead of a
+    graph.add_constraint("d", "a").unwrap();
+    assert_eq!(graph.order(), ["c", "d", "a"]);
+}
+
+#[test]
+fn test_self_loop() {
+    let mut graph = TaskGraph::new();
+    let err = graph.add_constraint("a", "a").unwrap_err();
+    assert_eq!(err.cycle, ["a", "a"]);
+    assert_eq!(err.blocked, ["a"]);
+    assert_eq!(graph.order(), ["a"]);
+    assert_eq!(graph.num_dependencies("a"), Some(0));
+}
@@ -1,30 +1,46 @@
+use cycle::CycleError;
 use serde_scan::scan;
 use std::cmp::Reverse;
-use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::collections::{BinaryHeap, HashMap};
 
-type Task = char;
+mod critical;
+mod cycle;
+mod dot;
+mod gantt;
+mod graph;
+mod orders;
+mod parse;
+mod policy;
+mod scheduler;
+
+type Task = String;
 type Result<T> = std::result::Result<T, std::boxed::Box<dyn std::error::Error>>;
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-struct InProgressTask {
-    task: Task,
-    remaining_time: usize,
-}
+const NUM_WORKERS: usize = 5;
+/// The base duration for graphs in the puzzle's format. Other formats give durations explicitly.
+const BASE_DURATION: usize = 60;
+const GANTT_WIDTH: usize = 100;
 
-impl InProgressTask {
-    fn new(task: Task) -> Self {
-        InProgressTask {
-            task,
-            remaining_time: (task as u8 - b'A' + 61) as usize,
-        }
-    }
-    fn is_complete(&self) -> bool {
-        self.remaining_time == 0
-    }
+/// The puzzle's example, for tests.
+#[cfg(test)]
+const EXAMPLE: &str = "Step C must be finished before step A can begin.
+Step C must be finished before step F can begin.
+Step A must be finished before step B can begin.
+Step A must be finished before step D can begin.
+Step B must be finished before step E can begin.
+Step D must be finished before step E can begin.
+Step F must be finished before step E can begin.";
 
-    fn log_work(&mut self) {
-        self.remaining_time -= 1;
-    }
+/// The dependency map for the puzzle's example, plus `extra` constraints in the same format.
+#[cfg(test)]
+fn example_dependency_map(extra: &[&str]) -> HashMap<Task, Vec<Task>> {
+    let constraints = EXAMPLE
+        .lines()
+        .chain(extra.iter().cloned())
+        .map(parse_constraint)
+        .collect::<Result<Vec<Constraint>>>()
+        .unwrap();
+    dependency_map_from_constraints(&constraints)
 }
 
 #[derive(Debug)]
@@ -33,76 +49,267 @@ struct Constraint {
     dependent: Task,
 }
 
-fn main() -> Result<()> {
-    let input = std::fs::read_to_string("input")?;
-    // TODO: don't build the actual vector here, build graph from an iterator
-    let constraints = input
-        .lines()
-        .map(parse_constraint)
-        .collect::<Result<Vec<Constraint>>>()?;
-    let task_to_dependents = dependency_map_from_constraints(&constraints);
-    println!("{:?}", part1(&task_to_dependents));
-    println!("{:?}", part2(&task_to_dependents));
-    Ok(())
+/// Reports errors with their `Display` form, which for a cycle is the cycle itself, rather than
+/// the `Debug` form returning them from `main` would give.
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
 }
 
-fn part1(task_to_dependents: &HashMap<Task, Vec<Task>>) -> String {
-    topo_sort(
-        task_to_dependents,
-        make_task_to_num_dependencies_map(&task_to_dependents),
-    )
-    .iter()
-    .collect()
-}
+fn run() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let num_workers = match args.iter().position(|arg| arg == "--workers") {
+        Some(idx) => args
+            .get(idx + 1)
+            .ok_or("--workers requires a count")?
+            .parse()?,
+        None => NUM_WORKERS,
+    };
+    if num_workers == 0 {
+        return Err(From::from("need at least one worker"));
+    }
+    let base_duration = match args.iter().position(|arg| arg == "--base-duration") {
+        Some(idx) => Some(
+            args.get(idx + 1)
+                .ok_or("--base-duration requires a number of seconds")?
+                .parse()?,
+        ),
+        None => None,
+    };
+    let policy_name = match args.iter().position(|arg| arg == "--policy") {
+        Some(idx) => Some(args.get(idx + 1).ok_or("--policy requires a name")?),
+        None => None,
+    };
+
+    let format = match args.iter().position(|arg| arg == "--format") {
+        Some(idx) => {
+            let name = args.get(idx + 1).ok_or("--format requires a name")?;
+            parse::Format::by_name(name).ok_or_else(|| format!("unknown format: {}", name))?
+        }
+        None => parse::Format::Puzzle,
+    };
+    let path = match args.iter().position(|arg| arg == "--input") {
+        Some(idx) => args.get(idx + 1).ok_or("--input requires a file")?,
+        None => "input",
+    };
 
-fn part2(task_to_dependents: &HashMap<Task, Vec<Task>>) -> usize {
-    let mut task_to_num_dependencies = make_task_to_num_dependencies_map(&task_to_dependents);
-    let mut num_idle_workers = 5;
-    let mut work_in_progress = Vec::with_capacity(5);
-    let mut time = 0;
-    let mut task_queue: VecDeque<Task> = task_to_num_dependencies
+    let input = std::fs::read_to_string(path)?;
+    let graph = format.parse(&input)?;
+    if args.iter().any(|arg| arg == "--edit") {
+        return run_edit(graph);
+    }
+    let mut task_to_dependents = dependency_map_from_constraints(&graph.constraints);
+    for task in &graph.tasks {
+        task_to_dependents.entry(task.clone()).or_default();
+    }
+    if let Some(task) = graph
+        .tasks
         .iter()
-        .filter(|(_task, &num_dependencies)| num_dependencies == 0)
-        .map(|(&task, _num_dependencies)| task)
-        .collect();
+        .find(|&task| !graph.durations.contains_key(task) && scheduler::letter_cost(task).is_none())
+    {
+        return Err(From::from(format!("no duration given for {}", task)));
+    }
+    let cost = |task: &str| {
+        graph
+            .durations
+            .get(task)
+            .cloned()
+            .or_else(|| scheduler::letter_cost(task))
+            .unwrap()
+    };
+    let base_duration = base_duration.unwrap_or(match format {
+        parse::Format::Puzzle => BASE_DURATION,
+        _ => 0,
+    });
 
-    while !task_queue.is_empty() || !work_in_progress.is_empty() {
-        println!("{}", time);
-        // assign work
-        let num_tasks_to_assign = std::cmp::min(num_idle_workers, task_queue.len());
-        for _ in 0..num_tasks_to_assign {
-            let task = task_queue.pop_front().unwrap();
-            work_in_progress.push(InProgressTask::new(task));
+    let ordering = part1(&task_to_dependents)?;
+    println!("{:?}", format_order(&ordering));
+    if args.iter().any(|arg| arg == "--count-orders") {
+        println!("{} orders", orders::count(&task_to_dependents)?);
+    }
+    if let Some(idx) = args.iter().position(|arg| arg == "--orders") {
+        let limit = args
+            .get(idx + 1)
+            .ok_or("--orders requires a number of orders")?
+            .parse()?;
+        for order in orders::Orders::new(&task_to_dependents)?.take(limit) {
+            println!("{}", format_order(&order));
         }
-        num_idle_workers -= num_tasks_to_assign;
-
-        // do work
-        for work in work_in_progress.iter_mut() {
-            work.log_work();
+    }
+    if let Some(idx) = args.iter().position(|arg| arg == "--check-order") {
+        let order = parse_order(args.get(idx + 1).ok_or("--check-order requires an order")?);
+        match orders::check(&task_to_dependents, &order) {
+            Ok(()) => println!("valid order"),
+            Err(err) => println!("invalid order: {}", err),
         }
-        let completed_tasks: HashSet<InProgressTask> = work_in_progress
-            .iter()
-            .filter(|task| task.is_complete())
-            .cloned()
-            .collect();
-
-        // update state of task queue and in progress tasks
-        for completed_task in &completed_tasks {
-            for dependent in &task_to_dependents[&completed_task.task] {
-                *task_to_num_dependencies.get_mut(dependent).unwrap() -= 1;
-                // task_to_num_dependencies[dependent] -= 1;
-                if task_to_num_dependencies[dependent] == 0 {
-                    task_queue.push_back(*dependent);
+    }
+    let scheduler = scheduler::Scheduler::new(num_workers, base_duration, cost);
+    if let Some(idx) = args.iter().position(|arg| arg == "--dot") {
+        let path = args.get(idx + 1).ok_or("--dot requires a file")?;
+        let duration = |task: &str| scheduler.duration(task);
+        let critical_path = if args.iter().any(|arg| arg == "--dot-critical-path") {
+            Some(critical::analyze(&task_to_dependents, &duration)?.critical_path)
+        } else {
+            None
+        };
+        let options = dot::DotOptions {
+            highlight_order: args.iter().any(|arg| arg == "--dot-order"),
+            critical_path,
+            cluster_by_depth: args.iter().any(|arg| arg == "--dot-clusters"),
+        };
+        let dot = dot::to_dot(&task_to_dependents, &ordering, &duration, &options);
+        std::fs::write(path, dot)?;
+    }
+    match policy_name.map(String::as_str) {
+        // compare every policy on the same graph
+        Some("all") => {
+            let seed = match args.iter().position(|arg| arg == "--seed") {
+                Some(idx) => args
+                    .get(idx + 1)
+                    .ok_or("--seed requires a number")?
+                    .parse()?,
+                None => 0,
+            };
+            let mut scheduler = scheduler;
+            for policy in policy::all_policies(seed) {
+                scheduler = scheduler.with_policy(policy);
+                println!(
+                    "{:>20}  {}",
+                    scheduler.policy().name(),
+                    part2(&task_to_dependents, &scheduler)?
+                );
+            }
+        }
+        name => {
+            let scheduler = match name {
+                Some(name) => scheduler.with_policy(
+                    policy::policy_by_name(name)
+                        .ok_or_else(|| format!("unknown policy: {}", name))?,
+                ),
+                None => scheduler,
+            };
+            let finish_time = part2(&task_to_dependents, &scheduler)?;
+            println!("{:?}", finish_time);
+            if args.iter().any(|arg| arg == "--critical-path") {
+                let analysis =
+                    critical::analyze(&task_to_dependents, &|task| scheduler.duration(task))?;
+                print!("{}", critical::format_analysis(&analysis));
+                let lower_bound = analysis.lower_bound(num_workers);
+                println!(
+                    "Lower bound with {} workers: {} (schedule is {} over)",
+                    num_workers,
+                    lower_bound,
+                    finish_time - lower_bound
+                );
+            }
+            write_gantt_charts(&args, &scheduler.schedule(&task_to_dependents)?)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads edits to the graph from stdin, one per line, and prints the order after each:
+///
+/// ```text
+/// add <dependency> <dependent>
+/// remove <dependency> <dependent>
+/// add-task <task>
+/// remove-task <task>
+/// ready
+/// ```
+///
+/// `ready` lists the tasks that have no dependencies.
+fn run_edit(parsed: parse::ParsedGraph) -> Result<()> {
+    let mut graph = graph::TaskGraph::from_constraints(parsed.constraints)?;
+    for task in &parsed.tasks {
+        graph.add_task(task);
+    }
+    println!("{}", format_order(graph.order()));
+
+    let mut line = String::new();
+    while std::io::stdin().read_line(&mut line)? > 0 {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let result = match words.as_slice() {
+            [] => Ok(()),
+            ["add", dependency, dependent] => graph
+                .add_constraint(dependency, dependent)
+                .map_err(|err| err.to_string()),
+            ["remove", dependency, dependent] => {
+                if graph.remove_constraint(dependency, dependent) {
+                    Ok(())
+                } else {
+                    Err(format!("no constraint {} -> {}", dependency, dependent))
+                }
+            }
+            ["add-task", task] => {
+                if graph.add_task(task) {
+                    Ok(())
+                } else {
+                    Err(format!("{} already exists", task))
                 }
             }
+            ["remove-task", task] => {
+                if graph.remove_task(task) {
+                    Ok(())
+                } else {
+                    Err(format!("no task {}", task))
+                }
+            }
+            ["ready"] => {
+                let ready: Vec<Task> = graph
+                    .order()
+                    .iter()
+                    .filter(|task| graph.num_dependencies(task) == Some(0))
+                    .cloned()
+                    .collect();
+                println!("ready: {}", format_order(&ready));
+                line.clear();
+                continue;
+            }
+            _ => Err(format!("unknown edit: {}", line.trim())),
+        };
+        match result {
+            Ok(()) => println!("{}", format_order(graph.order())),
+            Err(err) => println!("rejected: {}", err),
         }
-        work_in_progress.retain(|task| !completed_tasks.contains(task));
-        // work_in_progress.drain_filter(|task| completed_tasks.contains(task));
-        num_idle_workers += completed_tasks.len();
-        time += 1;
+        line.clear();
     }
+    Ok(())
+}
+
+/// Prints the charts asked for by `--gantt <table|text>` and writes the one asked for by
+/// `--svg <file>`.
+fn write_gantt_charts(args: &[String], schedule: &scheduler::Schedule) -> Result<()> {
+    if let Some(idx) = args.iter().position(|arg| arg == "--gantt") {
+        match args.get(idx + 1).map(String::as_str) {
+            Some("table") => print!("{}", gantt::table(schedule)),
+            Some("text") => print!("{}", gantt::text(schedule, GANTT_WIDTH)),
+            _ => return Err(From::from("--gantt requires table or text")),
+        }
+    }
+    if let Some(idx) = args.iter().position(|arg| arg == "--svg") {
+        let path = args.get(idx + 1).ok_or("--svg requires a file")?;
+        std::fs::write(path, gantt::svg(schedule))?;
+    }
+    Ok(())
+}
+
+fn part1(
+    task_to_dependents: &HashMap<Task, Vec<Task>>,
+) -> std::result::Result<Vec<Task>, CycleError> {
+    topo_sort(
+        task_to_dependents,
+        make_task_to_num_dependencies_map(task_to_dependents),
+    )
+}
 
-    time
+fn part2<C: Fn(&str) -> usize>(
+    task_to_dependents: &HashMap<Task, Vec<Task>>,
+    scheduler: &scheduler::Scheduler<C>,
+) -> std::result::Result<usize, CycleError> {
+    scheduler.finish_time(task_to_dependents)
 }
 
 fn make_task_to_num_dependencies_map(
@@ -110,11 +317,15 @@ fn make_task_to_num_dependencies_map(
 ) -> HashMap<Task, usize> {
     let mut task_to_num_dependencies: HashMap<Task, usize> =
         HashMap::with_capacity(task_to_dependents.len());
-    for (&dependency, dependents) in task_to_dependents {
+    for (dependency, dependents) in task_to_dependents {
         // ensure the dependency is in the map
-        task_to_num_dependencies.entry(dependency).or_insert(0);
+        task_to_num_dependencies
+            .entry(dependency.clone())
+            .or_insert(0);
         for dependent in dependents {
-            *task_to_num_dependencies.entry(*dependent).or_insert(0) += 1;
+            *task_to_num_dependencies
+                .entry(dependent.clone())
+                .or_insert(0) += 1;
         }
     }
     task_to_num_dependencies
@@ -123,47 +334,92 @@ fn make_task_to_num_dependencies_map(
 fn topo_sort(
     task_to_dependents: &HashMap<Task, Vec<Task>>,
     mut task_to_num_dependencies: HashMap<Task, usize>,
-) -> Vec<Task> {
+) -> std::result::Result<Vec<Task>, CycleError> {
     let mut queue = BinaryHeap::new();
     let mut ordering = Vec::with_capacity(task_to_dependents.len());
     let roots: Vec<Task> = task_to_num_dependencies
         .iter()
         .filter(|(_task, &num_dependencies)| num_dependencies == 0)
-        .map(|(&task, _num_dependencies)| task)
+        .map(|(task, _num_dependencies)| task.clone())
         .collect();
     for root in roots {
         queue.push(Reverse(root));
     }
 
     while let Some(Reverse(task)) = queue.pop() {
-        ordering.push(task);
         for dependent in task_to_dependents.get(&task).unwrap() {
             let num_dependencies = task_to_num_dependencies.get_mut(dependent).unwrap();
             *num_dependencies -= 1;
             if *num_dependencies == 0 {
-                queue.push(Reverse(*dependent))
+                queue.push(Reverse(dependent.clone()))
             }
         }
+        ordering.push(task);
     }
-    ordering
+    cycle::check(task_to_dependents, &task_to_num_dependencies)?;
+    Ok(ordering)
 }
 
 fn dependency_map_from_constraints(constraints: &[Constraint]) -> HashMap<Task, Vec<Task>> {
     let mut task_to_dependents = HashMap::with_capacity(26);
     for constraint in constraints {
         task_to_dependents
-            .entry(constraint.dependency)
+            .entry(constraint.dependency.clone())
             .or_insert_with(Vec::new)
-            .push(constraint.dependent);
+            .push(constraint.dependent.clone());
 
         // also make sure the dependent appears in the map
         task_to_dependents
-            .entry(constraint.dependent)
+            .entry(constraint.dependent.clone())
             .or_insert_with(Vec::new);
     }
     task_to_dependents
 }
 
+/// Single-letter tasks run together, as in the puzzle's answer. Longer names are separated by
+/// spaces.
+fn format_order(tasks: &[Task]) -> String {
+    let needs_quotes = |task: &Task| task.contains(|c: char| c == ',' || c.is_whitespace());
+    if tasks
+        .iter()
+        .all(|task| task.chars().count() == 1 && !needs_quotes(task))
+    {
+        return tasks.concat();
+    }
+    let names: Vec<String> = tasks
+        .iter()
+        .map(|task| {
+            if needs_quotes(task) {
+                format!("\"{}\"", task)
+            } else {
+                task.clone()
+            }
+        })
+        .collect();
+    names.join(" ")
+}
+
+/// The reverse of `format_order`: names separated by spaces or commas, in double quotes if they
+/// contain either, or otherwise one task per character.
+fn parse_order(s: &str) -> Vec<Task> {
+    if s.contains(|c: char| c == ',' || c == '"' || c.is_whitespace()) {
+        parse::split_unquoted(s, &[",", " ", "\t", "\n", "\r"])
+            .into_iter()
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                let is_quoted = name.len() >= 2 && name.starts_with('"') && name.ends_with('"');
+                if is_quoted {
+                    name[1..name.len() - 1].to_string()
+                } else {
+                    name.to_string()
+                }
+            })
+            .collect()
+    } else {
+        s.chars().map(|c| c.to_string()).collect()
+    }
+}
+
 fn parse_constraint(s: &str) -> Result<Constraint> {
     let (dependency, dependent): (Task, Task) =
         scan!("Step {} must be finished before step {} can begin." <- s)?;
@@ -172,3 +428,14 @@ fn parse_constraint(s: &str) -> Result<Constraint> {
         dependent,
     })
 }
+
+#[test]
+fn test_order_round_trip() {
+    let order: Vec<Task> = vec!["compile".into(), "link".into(), "test suite".into()];
+    assert_eq!(format_order(&order), "compile link \"test suite\"");
+    assert_eq!(parse_order(&format_order(&order)), order);
+    let order: Vec<Task> = "CABDFE".chars().map(|c| c.to_string()).collect();
+    assert_eq!(format_order(&order), "CABDFE");
+    assert_eq!(parse_order("CABDFE"), order);
+    assert_eq!(parse_order("C, A,B D F E"), order);
+}
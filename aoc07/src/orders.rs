@@ -0,0 +1,294 @@
+use crate::cycle::CycleError;
+use crate::{make_task_to_num_dependencies_map, topo_sort, Result, Task};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Counting works on bitmasks of finished tasks, so it can't handle more tasks than this.
+pub const MAX_COUNTED_TASKS: usize = 64;
+
+/// Number of different orders the tasks can be done in, one at a time.
+///
+/// Counts the orders from each set of finished tasks, remembering the count for every set it
+/// reaches. Only sets closed under dependencies can be reached, which keeps this manageable for
+/// graphs of a couple of dozen tasks unless they're very sparse.
+pub fn count(task_to_dependents: &HashMap<Task, Vec<Task>>) -> Result<u128> {
+    let mut tasks: Vec<&Task> = task_to_dependents.keys().collect();
+    if tasks.len() > MAX_COUNTED_TASKS {
+        return Err(From::from(format!(
+            "can only count orders of up to {} tasks, not {}",
+            MAX_COUNTED_TASKS,
+            tasks.len()
+        )));
+    }
+    topo_sort(
+        task_to_dependents,
+        make_task_to_num_dependencies_map(task_to_dependents),
+    )?;
+    tasks.sort_unstable();
+    let bits: HashMap<&Task, usize> = tasks
+        .iter()
+        .enumerate()
+        .map(|(bit, &task)| (task, bit))
+        .collect();
+    // the tasks each task depends on, as a mask
+    let mut dependency_masks = vec![0u64; tasks.len()];
+    for (dependency, dependents) in task_to_dependents {
+        for dependent in dependents {
+            dependency_masks[bits[dependent]] |= 1 << bits[dependency];
+        }
+    }
+
+    let all_done = if tasks.len() == 64 {
+        u64::MAX
+    } else {
+        (1 << tasks.len()) - 1
+    };
+    let mut memo = HashMap::new();
+    count_from(0, all_done, &dependency_masks, &mut memo)
+        .ok_or_else(|| From::from("too many orders to count"))
+}
+
+fn count_from(
+    done: u64,
+    all_done: u64,
+    dependency_masks: &[u64],
+    memo: &mut HashMap<u64, u128>,
+) -> Option<u128> {
+    if done == all_done {
+        return Some(1);
+    }
+    if let Some(&count) = memo.get(&done) {
+        return Some(count);
+    }
+    let mut count: u128 = 0;
+    for (bit, &dependencies) in dependency_masks.iter().enumerate() {
+        let is_ready = done & (1 << bit) == 0 && dependencies & !done == 0;
+        if is_ready {
+            let from_here = count_from(done | (1 << bit), all_done, dependency_masks, memo)?;
+            count = count.checked_add(from_here)?;
+        }
+    }
+    memo.insert(done, count);
+    Some(count)
+}
+
+/// Every order the tasks can be done in, one at a time, in alphabetical order. Each order is
+/// worked out only when it's asked for.
+pub struct Orders<'a> {
+    task_to_dependents: &'a HashMap<Task, Vec<Task>>,
+    /// Unfinished dependencies of every task not yet in the current order.
+    task_to_num_dependencies: HashMap<Task, usize>,
+    /// For every position in the current order, the tasks that were ready to go there and the
+    /// index of the one that did.
+    choices: Vec<(Vec<Task>, usize)>,
+    started: bool,
+}
+
+impl<'a> Orders<'a> {
+    pub fn new(
+        task_to_dependents: &'a HashMap<Task, Vec<Task>>,
+    ) -> std::result::Result<Self, CycleError> {
+        let task_to_num_dependencies = make_task_to_num_dependencies_map(task_to_dependents);
+        topo_sort(task_to_dependents, task_to_num_dependencies.clone())?;
+        Ok(Orders {
+            task_to_dependents,
+            task_to_num_dependencies,
+            choices: Vec::with_capacity(task_to_dependents.len()),
+            started: false,
+        })
+    }
+
+    fn place(&mut self, task: &Task) {
+        self.task_to_num_dependencies.remove(task);
+        for dependent in &self.task_to_dependents[task] {
+            *self.task_to_num_dependencies.get_mut(dependent).unwrap() -= 1;
+        }
+    }
+
+    fn unplace(&mut self, task: &Task) {
+        for dependent in &self.task_to_dependents[task] {
+            *self.task_to_num_dependencies.get_mut(dependent).unwrap() += 1;
+        }
+        self.task_to_num_dependencies.insert(task.clone(), 0);
+    }
+
+    /// Fills the rest of the current order with the first ready task at every step.
+    fn descend(&mut self) {
+        while !self.task_to_num_dependencies.is_empty() {
+            let mut ready: Vec<Task> = self
+                .task_to_num_dependencies
+                .iter()
+                .filter(|(_task, &num_dependencies)| num_dependencies == 0)
+                .map(|(task, _num_dependencies)| task.clone())
+                .collect();
+            ready.sort_unstable();
+            let first = ready[0].clone();
+            self.place(&first);
+            self.choices.push((ready, 0));
+        }
+    }
+
+    fn current(&self) -> Vec<Task> {
+        self.choices
+            .iter()
+            .map(|(ready, idx)| ready[*idx].clone())
+            .collect()
+    }
+}
+
+impl<'a> Iterator for Orders<'a> {
+    type Item = Vec<Task>;
+
+    fn next(&mut self) -> Option<Vec<Task>> {
+        if !self.started {
+            self.started = true;
+            self.descend();
+            return Some(self.current());
+        }
+        // take the last position that has another task it could try, and try the next one
+        while let Some((ready, idx)) = self.choices.pop() {
+            self.unplace(&ready[idx]);
+            if idx + 1 < ready.len() {
+                let next = ready[idx + 1].clone();
+                self.place(&next);
+                self.choices.push((ready, idx + 1));
+                self.descend();
+                return Some(self.current());
+            }
+        }
+        None
+    }
+}
+
+/// Why a proposed order can't be followed.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum OrderError {
+    /// `dependent` comes before `dependency`.
+    Violation {
+        dependency: Task,
+        dependent: Task,
+    },
+    UnknownTask(Task),
+    Repeated(Task),
+    Missing(Task),
+}
+
+impl fmt::Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OrderError::Violation {
+                dependency,
+                dependent,
+            } => write!(
+                f,
+                "{} must be finished before {} can begin",
+                dependency, dependent
+            ),
+            OrderError::UnknownTask(task) => write!(f, "unknown task {}", task),
+            OrderError::Repeated(task) => write!(f, "{} appears more than once", task),
+            OrderError::Missing(task) => write!(f, "{} is missing", task),
+        }
+    }
+}
+
+impl std::error::Error for OrderError {}
+
+/// Checks that `order` has every task exactly once, each after all of its dependencies. Reports
+/// the first unknown or repeated task if there is one, then the first task that comes before one
+/// of its dependencies, then the alphabetically first missing task.
+pub fn check(
+    task_to_dependents: &HashMap<Task, Vec<Task>>,
+    order: &[Task],
+) -> std::result::Result<(), OrderError> {
+    let mut task_to_dependencies: HashMap<&Task, Vec<&Task>> = HashMap::new();
+    for (dependency, dependents) in task_to_dependents {
+        for dependent in dependents {
+            task_to_dependencies
+                .entry(dependent)
+                .or_default()
+                .push(dependency);
+        }
+    }
+
+    let mut positions = HashMap::with_capacity(order.len());
+    for (position, task) in order.iter().enumerate() {
+        if !task_to_dependents.contains_key(task) {
+            return Err(OrderError::UnknownTask(task.clone()));
+        }
+        if positions.insert(task, position).is_some() {
+            return Err(OrderError::Repeated(task.clone()));
+        }
+    }
+    for task in order {
+        let mut dependencies = task_to_dependencies.remove(task).unwrap_or_default();
+        dependencies.sort_unstable();
+        for dependency in dependencies {
+            let is_before = positions
+                .get(dependency)
+                .is_some_and(|&position| position < positions[task]);
+            if !is_before {
+                return Err(OrderError::Violation {
+                    dependency: dependency.clone(),
+                    dependent: task.clone(),
+                });
+            }
+        }
+    }
+    let mut missing: Vec<&Task> = task_to_dependents
+        .keys()
+        .filter(|task| !positions.contains_key(task))
+        .collect();
+    missing.sort_unstable();
+    match missing.first() {
+        Some(task) => Err(OrderError::Missing((*task).clone())),
+        None => Ok(()),
+    }
+}
+
+#[test]
+fn test_example_orders() {
+    let task_to_dependents = crate::example_dependency_map(&[]);
+    assert_eq!(count(&task_to_dependents).unwrap(), 8);
+
+    let orders: Vec<String> = Orders::new(&task_to_dependents)
+        .unwrap()
+        .map(|order| order.concat())
+        .collect();
+    assert_eq!(orders.len(), 8);
+    assert_eq!(orders[0], "CABDFE");
+    let distinct: std::collections::HashSet<&String> = orders.iter().collect();
+    assert_eq!(distinct.len(), 8);
+    for order in &orders {
+        let order: Vec<Task> = order.chars().map(|c| c.to_string()).collect();
+        assert_eq!(check(&task_to_dependents, &order), Ok(()));
+    }
+}
+
+#[test]
+fn test_check_order() {
+    let task_to_dependents = crate::example_dependency_map(&[]);
+    let check_str = |order: &str| {
+        let order: Vec<Task> = order.chars().map(|c| c.to_string()).collect();
+        check(&task_to_dependents, &order)
+    };
+    assert_eq!(check_str("CABDFE"), Ok(()));
+    assert_eq!(
+        check_str("CBADFE"),
+        Err(OrderError::Violation {
+            dependency: "A".to_string(),
+            dependent: "B".to_string(),
+        })
+    );
+    assert_eq!(
+        check_str("CABDFEA"),
+        Err(OrderError::Repeated("A".to_string()))
+    );
+    assert_eq!(
+        check_str("CABDFEX"),
+        Err(OrderError::UnknownTask("X".to_string()))
+    );
+    assert_eq!(
+        check_str("CABDF"),
+        Err(OrderError::Missing("E".to_string()))
+    );
+}
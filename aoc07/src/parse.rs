@@ -0,0 +1,393 @@
+use crate::{parse_constraint, Constraint, Result, Task};
+use std::collections::{HashMap, HashSet};
+
+/// Everything read from a graph file. Tasks without a duration of their own take their cost
+/// from their name, as in the puzzle.
+#[derive(Debug, Default)]
+pub struct ParsedGraph {
+    /// Every task, in the order they first appear, including ones with no constraints.
+    pub tasks: Vec<Task>,
+    pub constraints: Vec<Constraint>,
+    pub durations: HashMap<Task, usize>,
+    known_tasks: HashSet<Task>,
+}
+
+impl ParsedGraph {
+    fn add_task(&mut self, task: &str) {
+        if self.known_tasks.insert(task.to_string()) {
+            self.tasks.push(task.to_string());
+        }
+    }
+
+    fn add_constraint(&mut self, dependency: &str, dependent: &str) {
+        self.add_task(dependency);
+        self.add_task(dependent);
+        self.constraints.push(Constraint {
+            dependency: dependency.to_string(),
+            dependent: dependent.to_string(),
+        });
+    }
+
+    fn set_duration(&mut self, task: &str, duration: usize) {
+        self.add_task(task);
+        self.durations.insert(task.to_string(), duration);
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Format {
+    /// `Step A must be finished before step B can begin.`
+    Puzzle,
+    /// `a -> b` for constraints and `a = 30` for durations.
+    EdgeList,
+    /// A Graphviz `digraph`, with durations given as `a [duration=30]`.
+    Dot,
+    /// `target: dependencies`, with a duration given as a `# duration=30` comment on the rule.
+    Makefile,
+}
+
+impl Format {
+    pub fn by_name(name: &str) -> Option<Format> {
+        match name {
+            "puzzle" => Some(Format::Puzzle),
+            "edges" => Some(Format::EdgeList),
+            "dot" => Some(Format::Dot),
+            "make" => Some(Format::Makefile),
+            _ => None,
+        }
+    }
+
+    pub fn parse(self, input: &str) -> Result<ParsedGraph> {
+        match self {
+            Format::Puzzle => parse_puzzle(input),
+            Format::EdgeList => parse_edge_list(input),
+            Format::Dot => parse_dot(input),
+            Format::Makefile => parse_makefile(input),
+        }
+    }
+}
+
+fn parse_puzzle(input: &str) -> Result<ParsedGraph> {
+    let mut graph = ParsedGraph::default();
+    for line in input.lines() {
+        let constraint = parse_constraint(line)?;
+        graph.add_constraint(&constraint.dependency, &constraint.dependent);
+    }
+    Ok(graph)
+}
+
+/// Also accepts chains, as in `a -> b -> c`, and lone task names. `#` starts a comment.
+/// Separators inside double-quoted names don't count.
+fn parse_edge_list(input: &str) -> Result<ParsedGraph> {
+    let mut graph = ParsedGraph::default();
+    for (line_idx, line) in input.lines().enumerate() {
+        let line = strip_comment(line, "#").trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(idx) = find_unquoted(line, "=") {
+            let duration = parse_duration(&line[idx + 1..])
+                .map_err(|err| format!("line {}: {}", line_idx + 1, err))?;
+            graph.set_duration(&parse_name(&line[..idx], line_idx)?, duration);
+            continue;
+        }
+        add_chain(
+            &mut graph,
+            split_unquoted(line, &["->"]).into_iter(),
+            line_idx,
+        )?;
+    }
+    Ok(graph)
+}
+
+/// Reads the statements of a single `digraph`: node statements with an optional `duration`
+/// attribute and edge statements, possibly chained. Graph, node and edge defaults, subgraphs and
+/// every other attribute are ignored. Separators inside double-quoted strings don't count.
+fn parse_dot(input: &str) -> Result<ParsedGraph> {
+    let mut graph = ParsedGraph::default();
+    let body_start = find_unquoted(input, "{").ok_or("expected a digraph { ... }")?;
+    let body_end = rfind_unquoted(input, "}").ok_or("expected a closing }")?;
+    let before_body = input[..body_start].trim();
+    if !before_body.starts_with("digraph") && !before_body.starts_with("strict digraph") {
+        return Err(From::from("expected a digraph"));
+    }
+
+    let first_line_idx = input[..body_start].matches('\n').count();
+    let lines = input[body_start + 1..body_end].lines();
+    for (line_idx, line) in (first_line_idx..).zip(lines) {
+        let line = strip_comment(line, "//");
+        for statement in split_unquoted(line, &[";"]) {
+            parse_dot_statement(&mut graph, statement.trim(), line_idx)?;
+        }
+    }
+    Ok(graph)
+}
+
+fn parse_dot_statement(graph: &mut ParsedGraph, statement: &str, line_idx: usize) -> Result<()> {
+    let statement = statement.trim_matches(|c| c == '{' || c == '}').trim();
+    if statement.is_empty() || statement.starts_with('#') {
+        return Ok(());
+    }
+    let (target, attributes) = match find_unquoted(statement, "[") {
+        Some(idx) => {
+            let end = rfind_unquoted(statement, "]")
+                .ok_or_else(|| format!("line {}: unterminated attribute list", line_idx + 1))?;
+            (statement[..idx].trim(), &statement[idx + 1..end])
+        }
+        None => (statement, ""),
+    };
+    let first_word = target.split_whitespace().next().unwrap_or("");
+    if ["graph", "node", "edge", "subgraph"].contains(&first_word)
+        || find_unquoted(target, "=").is_some()
+    {
+        return Ok(());
+    }
+
+    let attributes = parse_dot_attributes(attributes);
+    if find_unquoted(target, "->").is_some() {
+        let names = split_unquoted(target, &["->"]);
+        // edges that don't constrain the layout, such as the ones `--dot-order` draws, aren't
+        // dependencies
+        if attributes.contains(&("constraint", "false")) {
+            for name in names {
+                graph.add_task(&parse_name(name, line_idx)?);
+            }
+            return Ok(());
+        }
+        return add_chain(graph, names.into_iter(), line_idx);
+    }
+    let task = parse_name(target, line_idx)?;
+    graph.add_task(&task);
+    for (key, value) in attributes {
+        if key == "duration" {
+            let duration =
+                parse_duration(value).map_err(|err| format!("line {}: {}", line_idx + 1, err))?;
+            graph.set_duration(&task, duration);
+        }
+    }
+    Ok(())
+}
+
+/// The `key=value` pairs in an attribute list, with any quotes around the value removed.
+fn parse_dot_attributes(attributes: &str) -> Vec<(&str, &str)> {
+    split_unquoted(attributes, &[",", ";"])
+        .into_iter()
+        .filter_map(|attribute| attribute.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim().trim_matches('"')))
+        .collect()
+}
+
+/// Every dependency of a target must finish before the target can start. Recipe lines, variable
+/// assignments and special targets such as `.PHONY` are ignored.
+fn parse_makefile(input: &str) -> Result<ParsedGraph> {
+    let mut graph = ParsedGraph::default();
+    for (line_idx, line) in input.lines().enumerate() {
+        if line.starts_with('\t') {
+            continue;
+        }
+        let (rule, comment) = match line.split_once('#') {
+            Some((rule, comment)) => (rule, comment),
+            None => (line, ""),
+        };
+        if rule.trim().is_empty() || rule.contains('=') {
+            continue;
+        }
+        let (targets, dependencies) = rule
+            .split_once(':')
+            .ok_or_else(|| format!("line {}: expected target: dependencies", line_idx + 1))?;
+        let duration = match comment.split_once('=') {
+            Some((key, value)) if key.trim() == "duration" => Some(
+                parse_duration(value).map_err(|err| format!("line {}: {}", line_idx + 1, err))?,
+            ),
+            _ => None,
+        };
+        for target in targets.split_whitespace() {
+            if target.starts_with('.') {
+                continue;
+            }
+            graph.add_task(target);
+            for dependency in dependencies.split_whitespace() {
+                graph.add_constraint(dependency, target);
+            }
+            if let Some(duration) = duration {
+                graph.set_duration(target, duration);
+            }
+        }
+    }
+    Ok(graph)
+}
+
+fn add_chain<'a>(
+    graph: &mut ParsedGraph,
+    names: impl Iterator<Item = &'a str>,
+    line_idx: usize,
+) -> Result<()> {
+    let tasks = names
+        .map(|name| parse_name(name, line_idx))
+        .collect::<Result<Vec<Task>>>()?;
+    if tasks.len() == 1 {
+        graph.add_task(&tasks[0]);
+    }
+    for pair in tasks.windows(2) {
+        graph.add_constraint(&pair[0], &pair[1]);
+    }
+    Ok(())
+}
+
+/// A task name, optionally in double quotes. Unquoted names can't contain whitespace.
+fn parse_name(name: &str, line_idx: usize) -> Result<Task> {
+    let name = name.trim();
+    let unquoted = if name.len() >= 2 && name.starts_with('"') && name.ends_with('"') {
+        &name[1..name.len() - 1]
+    } else if name.contains(char::is_whitespace) || name.contains('"') {
+        return Err(From::from(format!(
+            "line {}: invalid task name {:?}",
+            line_idx + 1,
+            name
+        )));
+    } else {
+        name
+    };
+    if unquoted.is_empty() {
+        return Err(From::from(format!(
+            "line {}: missing task name",
+            line_idx + 1
+        )));
+    }
+    Ok(unquoted.to_string())
+}
+
+fn parse_duration(s: &str) -> std::result::Result<usize, String> {
+    s.trim()
+        .parse()
+        .map_err(|_| format!("invalid duration {:?}", s.trim()))
+}
+
+/// Byte offsets of the characters in `s` that aren't part of a double-quoted string. Inside one,
+/// a backslash escapes the character after it.
+fn unquoted_offsets(s: &str) -> Vec<usize> {
+    let mut offsets = Vec::new();
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (idx, c) in s.char_indices() {
+        if !in_quotes {
+            if c == '"' {
+                in_quotes = true;
+            } else {
+                offsets.push(idx);
+            }
+        } else if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            in_quotes = false;
+        }
+    }
+    offsets
+}
+
+fn find_unquoted(s: &str, pattern: &str) -> Option<usize> {
+    unquoted_offsets(s)
+        .into_iter()
+        .find(|&idx| s[idx..].starts_with(pattern))
+}
+
+fn rfind_unquoted(s: &str, pattern: &str) -> Option<usize> {
+    unquoted_offsets(s)
+        .into_iter()
+        .rev()
+        .find(|&idx| s[idx..].starts_with(pattern))
+}
+
+/// Splits `s` at every separator outside double-quoted strings.
+pub fn split_unquoted<'a>(s: &'a str, separators: &[&str]) -> Vec<&'a str> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    for idx in unquoted_offsets(s) {
+        if idx < start {
+            continue;
+        }
+        if let Some(separator) = separators
+            .iter()
+            .find(|separator| s[idx..].starts_with(**separator))
+        {
+            pieces.push(&s[start..idx]);
+            start = idx + separator.len();
+        }
+    }
+    pieces.push(&s[start..]);
+    pieces
+}
+
+/// Everything before the first `marker` outside double-quoted strings.
+fn strip_comment<'a>(line: &'a str, marker: &str) -> &'a str {
+    match find_unquoted(line, marker) {
+        Some(idx) => &line[..idx],
+        None => line,
+    }
+}
+
+#[test]
+fn test_parse_edge_list() {
+    let input = "# durations\n\"x -> y\" = 3\na -> b -> \"c#\" # a chain\nlone\n";
+    let graph = Format::EdgeList.parse(input).unwrap();
+    assert_eq!(graph.tasks, ["x -> y", "a", "b", "c#", "lone"]);
+    let constraints: Vec<(&str, &str)> = graph
+        .constraints
+        .iter()
+        .map(|c| (c.dependency.as_str(), c.dependent.as_str()))
+        .collect();
+    assert_eq!(constraints, [("a", "b"), ("b", "c#")]);
+    assert_eq!(graph.durations["x -> y"], 3);
+    assert!(Format::EdgeList.parse("a = soon").is_err());
+    assert!(Format::EdgeList.parse("a b -> c").is_err());
+}
+
+#[test]
+fn test_parse_dot() {
+    let input = r#"digraph tasks {
+    rankdir=LR;
+    node [shape=box];
+    "a;b" [label="x;y", duration=2];
+    b [label="a, duration=5", duration=3]; // c -> d
+    "a;b" -> b -> c [color=red];
+    c -> "a;b" [style=dashed, constraint=false];
+}"#;
+    let graph = Format::Dot.parse(input).unwrap();
+    assert_eq!(graph.tasks, ["a;b", "b", "c"]);
+    let constraints: Vec<(&str, &str)> = graph
+        .constraints
+        .iter()
+        .map(|c| (c.dependency.as_str(), c.dependent.as_str()))
+        .collect();
+    assert_eq!(constraints, [("a;b", "b"), ("b", "c")]);
+    assert_eq!(graph.durations["a;b"], 2);
+    assert_eq!(graph.durations["b"], 3);
+    assert!(!graph.durations.contains_key("c"));
+    assert!(Format::Dot.parse("graph tasks { a -- b }").is_err());
+}
+
+#[test]
+fn test_parse_makefile() {
+    let input = "CC = cc\n.PHONY: all\nall: app test # duration=1\napp: main.o util.o # duration=5\n\tcc -o app main.o util.o\nmain.o util.o:\n";
+    let graph = Format::Makefile.parse(input).unwrap();
+    assert_eq!(graph.tasks, ["all", "app", "test", "main.o", "util.o"]);
+    let constraints: Vec<(&str, &str)> = graph
+        .constraints
+        .iter()
+        .map(|c| (c.dependency.as_str(), c.dependent.as_str()))
+        .collect();
+    assert_eq!(
+        constraints,
+        [
+            ("app", "all"),
+            ("test", "all"),
+            ("main.o", "app"),
+            ("util.o", "app")
+        ]
+    );
+    assert_eq!(graph.durations["all"], 1);
+    assert_eq!(graph.durations["app"], 5);
+    assert!(Format::Makefile.parse("no colon here").is_err());
+}
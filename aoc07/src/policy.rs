@@ -0,0 +1,143 @@
+use crate::cycle::CycleError;
+use crate::{make_task_to_num_dependencies_map, topo_sort, Task};
+use std::collections::HashMap;
+
+/// Decides which ready task an idle worker picks up next.
+pub trait SchedulingPolicy {
+    fn name(&self) -> String;
+
+    /// The priority of every task. Idle workers take the ready task with the highest priority,
+    /// breaking ties alphabetically.
+    fn priorities(
+        &self,
+        task_to_dependents: &HashMap<Task, Vec<Task>>,
+        duration: &dyn Fn(&str) -> usize,
+    ) -> Result<HashMap<Task, u64>, CycleError>;
+}
+
+/// Ready tasks in alphabetical order, as the puzzle does.
+pub struct Alphabetical;
+/// The longest ready task first.
+pub struct LongestProcessingTime;
+/// The ready task with the longest chain of work after it, counting its own duration.
+pub struct CriticalPath;
+/// Ready tasks in an order shuffled by `seed`, the same order every time for the same seed.
+pub struct Random {
+    pub seed: u64,
+}
+
+impl SchedulingPolicy for Alphabetical {
+    fn name(&self) -> String {
+        "alphabetical".to_string()
+    }
+
+    fn priorities(
+        &self,
+        task_to_dependents: &HashMap<Task, Vec<Task>>,
+        _duration: &dyn Fn(&str) -> usize,
+    ) -> Result<HashMap<Task, u64>, CycleError> {
+        Ok(task_to_dependents
+            .keys()
+            .map(|task| (task.clone(), 0))
+            .collect())
+    }
+}
+
+impl SchedulingPolicy for LongestProcessingTime {
+    fn name(&self) -> String {
+        "longest-first".to_string()
+    }
+
+    fn priorities(
+        &self,
+        task_to_dependents: &HashMap<Task, Vec<Task>>,
+        duration: &dyn Fn(&str) -> usize,
+    ) -> Result<HashMap<Task, u64>, CycleError> {
+        Ok(task_to_dependents
+            .keys()
+            .map(|task| (task.clone(), duration(task) as u64))
+            .collect())
+    }
+}
+
+impl SchedulingPolicy for CriticalPath {
+    fn name(&self) -> String {
+        "critical-path".to_string()
+    }
+
+    fn priorities(
+        &self,
+        task_to_dependents: &HashMap<Task, Vec<Task>>,
+        duration: &dyn Fn(&str) -> usize,
+    ) -> Result<HashMap<Task, u64>, CycleError> {
+        let ordering = topo_sort(
+            task_to_dependents,
+            make_task_to_num_dependencies_map(task_to_dependents),
+        )?;
+        // every task's dependents come after it, so go backwards
+        let mut priorities = HashMap::with_capacity(ordering.len());
+        for task in ordering.into_iter().rev() {
+            let longest_after = task_to_dependents[&task]
+                .iter()
+                .map(|dependent| priorities[dependent])
+                .max()
+                .unwrap_or(0);
+            let priority = duration(&task) as u64 + longest_after;
+            priorities.insert(task, priority);
+        }
+        Ok(priorities)
+    }
+}
+
+impl SchedulingPolicy for Random {
+    fn name(&self) -> String {
+        format!("random:{}", self.seed)
+    }
+
+    fn priorities(
+        &self,
+        task_to_dependents: &HashMap<Task, Vec<Task>>,
+        _duration: &dyn Fn(&str) -> usize,
+    ) -> Result<HashMap<Task, u64>, CycleError> {
+        Ok(task_to_dependents
+            .keys()
+            .map(|task| {
+                let hash = task
+                    .bytes()
+                    .fold(self.seed, |hash, byte| splitmix64(hash ^ byte as u64));
+                (task.clone(), hash)
+            })
+            .collect())
+    }
+}
+
+/// A well-mixed hash of `x`, so nearby seeds and tasks give unrelated priorities.
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+pub fn all_policies(seed: u64) -> Vec<Box<dyn SchedulingPolicy>> {
+    vec![
+        Box::new(Alphabetical),
+        Box::new(LongestProcessingTime),
+        Box::new(CriticalPath),
+        Box::new(Random { seed }),
+    ]
+}
+
+/// Looks a policy up by name. `random` takes an optional seed, as in `random:42`.
+pub fn policy_by_name(name: &str) -> Option<Box<dyn SchedulingPolicy>> {
+    match name {
+        "random" => Some(Box::new(Random { seed: 0 })),
+        _ if name.starts_with("random:") => {
+            let seed = name["random:".len()..].parse().ok()?;
+            Some(Box::new(Random { seed }))
+        }
+        _ => all_policies(0)
+            .into_iter()
+            .find(|policy| policy.name() == name),
+    }
+}
@@ -1,3 +1,5 @@
+mod value;
+
 type Result<T> = std::result::Result<T, std::boxed::Box<dyn std::error::Error>>;
 #[derive(Debug)]
 struct Node {
@@ -5,66 +7,215 @@ struct Node {
     metadata_entries: Vec<usize>,
 }
 
-fn main() -> Result<()> {
+/// Dropping a tree would otherwise recurse once per level, so move descendants onto a heap stack
+/// and drop them one at a time.
+impl Drop for Node {
+    fn drop(&mut self) {
+        let mut stack = std::mem::take(&mut self.children);
+        while let Some(mut node) = stack.pop() {
+            stack.append(&mut node.children);
+        }
+    }
+}
+
+/// Reports errors with their `Display` form, which says where a parse went wrong, rather than the
+/// `Debug` form returning them from `main` would give.
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
     let input = std::fs::read_to_string("input")?;
     let root = build_tree(&input)?;
     println!("part 1: {}", part1(&root));
-    println!("part 2: {}", part2(&root));
+    let values = value::Values::new(&root)?;
+    println!("part 2: {}", values.root_value());
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--explain") {
+        print!("{}", value::format_explanations(&values.explain()));
+    }
+    if let Some(idx) = args.iter().position(|arg| arg == "--path") {
+        let number: usize = args
+            .get(idx + 1)
+            .ok_or("--path requires the number of an explained node")?
+            .parse()?;
+        let explanations = values.explain();
+        if number >= explanations.len() {
+            return Err(From::from(format!("no explained node #{}", number)));
+        }
+        println!(
+            "#{} is {}",
+            number,
+            value::format_path(&value::path(&explanations, number))
+        );
+    }
     Ok(())
 }
 
 fn part1(root: &Node) -> usize {
-    sum_metadata_rec(root)
+    sum_metadata(root)
 }
 
-fn sum_metadata_rec(node: &Node) -> usize {
-    node.metadata_entries.iter().sum::<usize>()
-        + node.children.iter().map(sum_metadata_rec).sum::<usize>()
+/// Walks the tree with an explicit stack so deep trees can't overflow the call stack.
+fn sum_metadata(root: &Node) -> usize {
+    let mut sum = 0;
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        sum += node.metadata_entries.iter().sum::<usize>();
+        stack.extend(&node.children);
+    }
+    sum
 }
 
-fn part2(root: &Node) -> usize {
-    calculate_value_rec(&root)
+/// Why the input isn't a valid license file. Token indices count from 0.
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum ParseError {
+    /// The token isn't a non-negative number.
+    Malformed { index: usize, token: String },
+    /// The input ended where the token at this index should have been.
+    Missing { index: usize },
+    /// The root node ends before the token at this index.
+    TrailingData { index: usize },
 }
 
-fn calculate_value_rec(node: &Node) -> usize {
-    if node.children.is_empty() {
-        node.metadata_entries.iter().sum()
-    } else {
-        node.metadata_entries
-            .iter()
-            .map(|&i| {
-                node.children
-                    .get(i - 1)
-                    .map(calculate_value_rec)
-                    .unwrap_or(0)
-            })
-            .sum()
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParseError::Malformed { index, token } => {
+                write!(f, "token {}: expected a number, found {:?}", index, token)
+            }
+            ParseError::Missing { index } => write!(f, "token {}: unexpected end of input", index),
+            ParseError::TrailingData { index } => {
+                write!(f, "token {}: unexpected data after the root node", index)
+            }
+        }
     }
 }
 
-fn build_tree(input: &str) -> Result<Node> {
-    build_tree_rec(&mut input.split_ascii_whitespace().map(|s| s.parse::<usize>()))
+impl std::error::Error for ParseError {}
+
+/// A node whose header has been read but whose children and metadata haven't all been.
+struct PartialNode {
+    num_children: usize,
+    num_metadata_entries: usize,
+    children: Vec<Node>,
+}
+
+struct Tokens<'a> {
+    tokens: Vec<&'a str>,
+    /// Index of the next token to read.
+    index: usize,
 }
 
-fn build_tree_rec<I>(vals: &mut I) -> Result<Node>
-where
-    I: Iterator<Item = std::result::Result<usize, std::num::ParseIntError>>,
-{
-    let num_children = vals.next().ok_or("incomplete input")??;
-    let num_metadata_entries = vals.next().ok_or("incomplete input")??;
-    let mut children = Vec::with_capacity(num_children);
-    let mut metadata_entries = Vec::with_capacity(num_metadata_entries);
-
-    for _ in 0..num_children {
-        children.push(build_tree_rec(vals)?);
+impl<'a> Tokens<'a> {
+    fn next_value(&mut self) -> std::result::Result<usize, ParseError> {
+        let index = self.index;
+        let token = self
+            .tokens
+            .get(index)
+            .ok_or(ParseError::Missing { index })?;
+        let value = token.parse().map_err(|_| ParseError::Malformed {
+            index,
+            token: token.to_string(),
+        })?;
+        self.index += 1;
+        Ok(value)
+    }
+
+    fn read_header(&mut self) -> std::result::Result<PartialNode, ParseError> {
+        let num_children = self.next_value()?;
+        let num_metadata_entries = self.next_value()?;
+        // every child takes at least two tokens, so don't trust a count that can't fit
+        let max_children = (self.tokens.len() - self.index) / 2;
+        Ok(PartialNode {
+            num_children,
+            num_metadata_entries,
+            children: Vec::with_capacity(num_children.min(max_children)),
+        })
     }
+}
+
+/// Builds the tree with an explicit stack of the nodes still being read, so however deep the tree
+/// is, reading it takes constant space on the call stack.
+fn build_tree(input: &str) -> std::result::Result<Node, ParseError> {
+    let mut tokens = Tokens {
+        tokens: input.split_ascii_whitespace().collect(),
+        index: 0,
+    };
+    let mut stack = vec![tokens.read_header()?];
+    loop {
+        let top = stack.last().unwrap();
+        if top.children.len() < top.num_children {
+            stack.push(tokens.read_header()?);
+            continue;
+        }
 
-    for _ in 0..num_metadata_entries {
-        metadata_entries.push(vals.next().ok_or("incomplete input")??);
+        let partial = stack.pop().unwrap();
+        let remaining = tokens.tokens.len() - tokens.index;
+        let mut metadata_entries = Vec::with_capacity(partial.num_metadata_entries.min(remaining));
+        for _ in 0..partial.num_metadata_entries {
+            metadata_entries.push(tokens.next_value()?);
+        }
+        let node = Node {
+            children: partial.children,
+            metadata_entries,
+        };
+        match stack.last_mut() {
+            Some(parent) => parent.children.push(node),
+            None if tokens.index < tokens.tokens.len() => {
+                return Err(ParseError::TrailingData {
+                    index: tokens.index,
+                })
+            }
+            None => return Ok(node),
+        }
     }
-    // let num_metadata_entries = vals.nex
-    Ok(Node {
-        children,
-        metadata_entries,
-    })
+}
+
+#[test]
+fn test_example() {
+    let root = build_tree("2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2").unwrap();
+    assert_eq!(part1(&root), 138);
+    assert_eq!(value::Values::new(&root).unwrap().root_value(), 66);
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!(
+        build_tree("1 1 0 x 5 5").unwrap_err(),
+        ParseError::Malformed {
+            index: 3,
+            token: "x".to_string()
+        }
+    );
+    assert_eq!(
+        build_tree("1 1 0 1 5").unwrap_err(),
+        ParseError::Missing { index: 5 }
+    );
+    assert_eq!(
+        build_tree("0 1 5 0").unwrap_err(),
+        ParseError::TrailingData { index: 3 }
+    );
+}
+
+#[test]
+fn test_deep_chain() {
+    let depth = 100_000;
+    let mut input = "1 1 ".repeat(depth);
+    input.push_str("0 1 5");
+    input.push_str(&" 1".repeat(depth));
+    let root = build_tree(&input).unwrap();
+    assert_eq!(part1(&root), depth + 5);
+    let values = value::Values::new(&root).unwrap();
+    assert_eq!(values.root_value(), 5);
+    let explanations = values.explain();
+    assert_eq!(explanations.len(), depth + 1);
+    assert_eq!(value::path(&explanations, depth), vec![1; depth]);
+    assert_eq!(
+        value::format_explanations(&explanations).lines().count(),
+        2 * (depth + 1)
+    );
 }
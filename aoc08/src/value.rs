@@ -0,0 +1,219 @@
+use crate::{Node, Result};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+
+/// What one metadata entry adds to its node's value.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Reference {
+    /// The node has no children, so the entry counts for itself.
+    Metadata(usize),
+    /// The entry refers to the child at this 1-based index, and adds its value.
+    Child { index: usize, value: usize },
+    /// Entries of 0 don't refer to any child, and add nothing.
+    Zero,
+    /// The node has no child at this index, so the entry adds nothing.
+    OutOfRange(usize),
+}
+
+/// How a node's value is made up.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Explanation {
+    /// Where the parent's explanation is among all of them, and the node's 1-based index among
+    /// the parent's children. `None` for the root.
+    pub parent: Option<(usize, usize)>,
+    pub value: usize,
+    /// One for each of the node's metadata entries, in order.
+    pub references: Vec<Reference>,
+}
+
+/// The value of every node in a tree, each worked out once.
+pub struct Values<'a> {
+    /// Every node in breadth-first order, so each node's children are next to each other and
+    /// come after it.
+    nodes: Vec<&'a Node>,
+    /// Index in `nodes` of each node's first child.
+    first_child: Vec<usize>,
+    values: Vec<usize>,
+}
+
+impl<'a> Values<'a> {
+    /// Works out values from the deepest nodes up, so every child's value is known by the time
+    /// its parent refers to it, without any recursion. Fails if a value doesn't fit in a `usize`.
+    pub fn new(root: &'a Node) -> Result<Self> {
+        let mut nodes = vec![root];
+        let mut first_child = Vec::new();
+        let mut idx = 0;
+        while idx < nodes.len() {
+            first_child.push(nodes.len());
+            let node = nodes[idx];
+            nodes.extend(&node.children);
+            idx += 1;
+        }
+
+        let mut values = vec![0; nodes.len()];
+        for idx in (0..nodes.len()).rev() {
+            let mut value: usize = 0;
+            for reference in references(nodes[idx], first_child[idx], &values) {
+                let added = match reference {
+                    Reference::Metadata(entry) => entry,
+                    Reference::Child { value, .. } => value,
+                    Reference::Zero | Reference::OutOfRange(_) => 0,
+                };
+                value = value.checked_add(added).ok_or("node value is too large")?;
+            }
+            values[idx] = value;
+        }
+        Ok(Values {
+            nodes,
+            first_child,
+            values,
+        })
+    }
+
+    pub fn root_value(&self) -> usize {
+        self.values[0]
+    }
+
+    /// Explains the root's value and the value of every node it refers to, directly or not,
+    /// each once, in breadth-first order, so the root comes first and every node comes after its
+    /// parent.
+    pub fn explain(&self) -> Vec<Explanation> {
+        let mut explanations = Vec::new();
+        let mut explained = vec![false; self.nodes.len()];
+        explained[0] = true;
+        let mut queue = VecDeque::new();
+        queue.push_back((0, None));
+        while let Some((idx, parent)) = queue.pop_front() {
+            let references = references(self.nodes[idx], self.first_child[idx], &self.values);
+            for reference in &references {
+                if let Reference::Child { index, .. } = *reference {
+                    let child_idx = self.first_child[idx] + index - 1;
+                    if !explained[child_idx] {
+                        explained[child_idx] = true;
+                        queue.push_back((child_idx, Some((explanations.len(), index))));
+                    }
+                }
+            }
+            explanations.push(Explanation {
+                parent,
+                value: self.values[idx],
+                references,
+            });
+        }
+        explanations
+    }
+}
+
+/// What each of `node`'s metadata entries refers to, given the values of its children, which
+/// start at `first_child` in `values`.
+fn references(node: &Node, first_child: usize, values: &[usize]) -> Vec<Reference> {
+    node.metadata_entries
+        .iter()
+        .map(|&entry| {
+            if node.children.is_empty() {
+                Reference::Metadata(entry)
+            } else if entry == 0 {
+                Reference::Zero
+            } else if entry > node.children.len() {
+                Reference::OutOfRange(entry)
+            } else {
+                Reference::Child {
+                    index: entry,
+                    value: values[first_child + entry - 1],
+                }
+            }
+        })
+        .collect()
+}
+
+/// The 1-based child indices leading from the root to the node explained at `idx`.
+pub fn path(explanations: &[Explanation], mut idx: usize) -> Vec<usize> {
+    let mut path = Vec::new();
+    while let Some((parent, index)) = explanations[idx].parent {
+        path.push(index);
+        idx = parent;
+    }
+    path.reverse();
+    path
+}
+
+pub fn format_path(path: &[usize]) -> String {
+    let mut formatted = "root".to_string();
+    for index in path {
+        write!(formatted, ".{}", index).unwrap();
+    }
+    formatted
+}
+
+/// Numbers the explained nodes from `#0` for the root, and names each node by its parent's
+/// number and its index among the parent's children, so the output stays proportional to the
+/// number of metadata entries however deep the tree is.
+pub fn format_explanations(explanations: &[Explanation]) -> String {
+    let numbers: HashMap<(usize, usize), usize> = explanations
+        .iter()
+        .enumerate()
+        .filter_map(|(number, explanation)| Some((explanation.parent?, number)))
+        .collect();
+    let mut formatted = String::new();
+    for (number, explanation) in explanations.iter().enumerate() {
+        match explanation.parent {
+            Some((parent, index)) => writeln!(
+                formatted,
+                "#{} (child {} of #{}) = {}",
+                number, index, parent, explanation.value
+            ),
+            None => writeln!(formatted, "#{} (root) = {}", number, explanation.value),
+        }
+        .unwrap();
+        for reference in &explanation.references {
+            match *reference {
+                Reference::Metadata(entry) => writeln!(formatted, "  entry {}", entry),
+                Reference::Child { index, value } => writeln!(
+                    formatted,
+                    "  entry {} -> #{} = {}",
+                    index,
+                    numbers[&(number, index)],
+                    value
+                ),
+                Reference::Zero => writeln!(formatted, "  entry 0 -> nothing"),
+                Reference::OutOfRange(entry) => {
+                    writeln!(formatted, "  entry {} -> no such child", entry)
+                }
+            }
+            .unwrap();
+        }
+    }
+    formatted
+}
+
+#[test]
+fn test_zero_out_of_range_and_repeated_entries() {
+    // the root has two children, worth 7 and 3, and metadata 0 3 1 1 2
+    let root = crate::build_tree("2 5 0 1 7 0 2 1 2 0 3 1 1 2").unwrap();
+    let values = Values::new(&root).unwrap();
+    assert_eq!(values.root_value(), 17);
+
+    let explanations = values.explain();
+    assert_eq!(explanations.len(), 3);
+    assert_eq!(
+        explanations[0],
+        Explanation {
+            parent: None,
+            value: 17,
+            references: vec![
+                Reference::Zero,
+                Reference::OutOfRange(3),
+                Reference::Child { index: 1, value: 7 },
+                Reference::Child { index: 1, value: 7 },
+                Reference::Child { index: 2, value: 3 },
+            ],
+        }
+    );
+    assert_eq!(explanations[1].parent, Some((0, 1)));
+    assert_eq!(explanations[1].references, [Reference::Metadata(7)]);
+    assert_eq!(path(&explanations, 2), [2]);
+    assert_eq!(
+        explanations[2].references,
+        [Reference::Metadata(1), Reference::Metadata(2)]
+    );
+}